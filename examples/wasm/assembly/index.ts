@@ -2,6 +2,10 @@ export enum MIDIMessageType {
   NoteOn = 0,
   NoteOff = 1,
   Controller = 3,
+  // The value (v3) of these goes up to 16383, and so does the parameter number (v2) of NRPNs and RPNs
+  Controller14 = 7,
+  Nrpn = 8,
+  Rpn = 9,
 }
 
 /**
@@ -1,9 +1,11 @@
 use std::collections::HashMap;
 
 use crate::{
-    midi_event::MIDIRouterEvent,
+    midi_event::{AggregateOptions, MIDIRouterEvent, NoteOffEncoding},
+    note_ledger::NoteLedgerHandle,
+    panic::PanicOptions,
     pipeline::{Pipeline, PipelineOptions},
-    tempo::Clock,
+    tempo::{Clock, PPQN},
 };
 use futures::{future::select_all, StreamExt};
 use schemars::JsonSchema;
@@ -11,9 +13,62 @@ use serde::Deserialize;
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct AppConfig {
-    pub input_devices: HashMap<String, String>,
-    pub output_devices: HashMap<String, String>,
+    pub input_devices: HashMap<String, InputDeviceConfig>,
+    pub output_devices: HashMap<String, OutputDeviceConfig>,
     pub pipelines: Vec<PipelineOptions>,
+    pub panic: Option<PanicOptions>,
+}
+
+/// Either just the device name, or the name along with how to read from the device
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum InputDeviceConfig {
+    Name(String),
+    Options {
+        name: String,
+        aggregate: Option<AggregateOptions>,
+    },
+}
+
+impl InputDeviceConfig {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Name(name) | Self::Options { name, .. } => name.clone(),
+        }
+    }
+
+    pub fn aggregate_options(&self) -> Option<AggregateOptions> {
+        match self {
+            Self::Name(_) => None,
+            Self::Options { aggregate, .. } => aggregate.clone(),
+        }
+    }
+}
+
+/// Either just the device name, or the name along with how to talk to the device
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum OutputDeviceConfig {
+    Name(String),
+    Options {
+        name: String,
+        note_off: Option<NoteOffEncoding>,
+    },
+}
+
+impl OutputDeviceConfig {
+    pub fn name(&self) -> String {
+        match self {
+            Self::Name(name) | Self::Options { name, .. } => name.clone(),
+        }
+    }
+
+    pub fn note_off_encoding(&self) -> NoteOffEncoding {
+        match self {
+            Self::Name(_) => NoteOffEncoding::default(),
+            Self::Options { note_off, .. } => note_off.unwrap_or_default(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -22,9 +77,18 @@ pub enum MIDIMapperEvent {
     RouterMessage(MIDIRouterEvent),
 }
 
+/// An event on its way out, along with the pipeline that produced it
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EgressMessage {
+    /// Position of the pipeline in the config, which stays unique even when names clash
+    pub pipeline: usize,
+    pub message: MIDIRouterEvent,
+}
+
 pub struct App {
-    pub egress: Option<flume::Sender<MIDIRouterEvent>>,
+    pub egress: Option<flume::Sender<EgressMessage>>,
     pub ingress: Option<flume::Receiver<MIDIRouterEvent>>,
+    pub note_ledger: Option<NoteLedgerHandle>,
     pub pipelines: Vec<Pipeline>,
 }
 
@@ -33,6 +97,7 @@ impl App {
         App {
             ingress: None,
             egress: None,
+            note_ledger: None,
             pipelines: config
                 .pipelines
                 .into_iter()
@@ -51,14 +116,19 @@ impl App {
         self.ingress = Some(ingress);
     }
 
-    pub fn set_egress(&mut self, egress: flume::Sender<MIDIRouterEvent>) {
+    pub fn set_egress(&mut self, egress: flume::Sender<EgressMessage>) {
         self.egress = Some(egress);
     }
 
+    pub fn set_note_ledger(&mut self, note_ledger: NoteLedgerHandle) {
+        self.note_ledger = Some(note_ledger);
+    }
+
     pub async fn run(self) -> Option<()> {
-        let (clock, clock_handler) = Clock::new(60.0, 96.0);
+        let (clock, clock_handler) = Clock::new(60.0, PPQN);
         let ingress = self.ingress.unwrap();
         let egress = self.egress.unwrap();
+        let note_ledger = self.note_ledger.unwrap();
 
         tokio::spawn(async move { clock.start().await });
 
@@ -80,15 +150,17 @@ impl App {
         let pipeline_futures = self
             .pipelines
             .into_iter()
-            .map(|p| {
+            .enumerate()
+            .map(|(pipeline, p)| {
                 let egress = egress.clone();
                 let local_clock = clock_handler.clone();
+                let local_note_ledger = note_ledger.clone();
                 tokio::spawn(async move {
-                    let mut result_stream = p.listen(local_clock).await;
+                    let mut result_stream = p.listen(local_clock, local_note_ledger).await;
 
                     while let Some(x) = result_stream.next().await {
                         if let MIDIMapperEvent::RouterMessage(message) = x {
-                            egress.send(message).unwrap();
+                            egress.send(EgressMessage { pipeline, message }).unwrap();
                         }
                     }
                 })
@@ -0,0 +1,61 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+// How steep the exponential and logarithmic curves are
+const STEEPNESS: f64 = 3.0;
+
+/// Shapes used to map a value onto another, both normalized between 0 and 1
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub enum Curve {
+    #[default]
+    Linear,
+    /// Slow at first, then quickly rises
+    Exponential,
+    /// Quickly rises at first, then slows down
+    Logarithmic,
+    /// Slow at both ends, fast in the middle
+    #[serde(alias = "SCurve")]
+    Sigmoid,
+}
+
+impl Curve {
+    pub fn apply(&self, x: f64) -> f64 {
+        let x = x.clamp(0.0, 1.0);
+
+        match self {
+            Self::Linear => x,
+            Self::Exponential => ((STEEPNESS * x).exp() - 1.0) / (STEEPNESS.exp() - 1.0),
+            Self::Logarithmic => (1.0 + (STEEPNESS.exp() - 1.0) * x).ln() / STEEPNESS,
+            Self::Sigmoid => x * x * (3.0 - 2.0 * x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Curve;
+
+    #[test]
+    fn curves_keep_their_ends() {
+        [
+            Curve::Linear,
+            Curve::Exponential,
+            Curve::Logarithmic,
+            Curve::Sigmoid,
+        ]
+        .iter()
+        .for_each(|curve| {
+            assert!(curve.apply(0.0).abs() < 1e-9);
+            assert!((curve.apply(1.0) - 1.0).abs() < 1e-9);
+        });
+    }
+
+    #[test]
+    fn curves_bend_the_middle() {
+        assert_eq!(Curve::Linear.apply(0.5), 0.5);
+        assert!(Curve::Exponential.apply(0.5) < 0.5);
+        assert!(Curve::Logarithmic.apply(0.5) > 0.5);
+        assert!(Curve::Sigmoid.apply(0.25) < 0.25);
+        assert!(Curve::Sigmoid.apply(0.75) > 0.75);
+    }
+}
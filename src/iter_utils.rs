@@ -1,3 +1,4 @@
+use rand::Rng;
 use schemars::JsonSchema;
 use serde::Deserialize;
 use std::{iter, ops::Range};
@@ -7,6 +8,10 @@ pub enum CycleDirection {
     Forward,
     Backward,
     PingPong,
+    /// A random item every time
+    Random,
+    /// Randomly moves one item forwards or backwards every time
+    RandomWalk,
 }
 
 #[derive(Debug)]
@@ -22,7 +27,7 @@ pub struct Cycle<I> {
 impl<I> Cycle<I> {
     pub fn new(vec: Vec<I>, direction: CycleDirection, repeat: Option<u64>) -> Cycle<I> {
         let play_head = match direction {
-            CycleDirection::Backward => vec.len() - 1,
+            CycleDirection::Backward => vec.len().saturating_sub(1),
             _ => 0,
         };
 
@@ -41,6 +46,10 @@ impl<I> Cycle<I> {
         self.play_head = 0;
     }
 
+    pub fn set_direction(&mut self, direction: CycleDirection) {
+        self.direction = direction;
+    }
+
     pub fn next(&mut self) -> &I {
         let current = self.vec.get(self.play_head).unwrap();
 
@@ -85,6 +94,22 @@ impl<I> Cycle<I> {
                     self.play_head.saturating_add(1)
                 }
             }
+
+            CycleDirection::Random => rand::thread_rng().gen_range(0..=max),
+
+            CycleDirection::RandomWalk => {
+                if max == 0 {
+                    0
+                } else if self.play_head == 0 {
+                    1
+                } else if self.play_head >= max {
+                    max - 1
+                } else if rand::thread_rng().gen_bool(0.5) {
+                    self.play_head + 1
+                } else {
+                    self.play_head - 1
+                }
+            }
         };
 
         current
@@ -169,4 +194,17 @@ mod tests {
         assert_eq!(c.next(), &1);
         assert_eq!(c.next(), &2);
     }
+
+    #[test]
+    fn random_walk() {
+        let mut c = Cycle::new(vec![1i32, 2, 3, 4], CycleDirection::RandomWalk, None);
+        let mut previous = *c.next();
+
+        (0..100).for_each(|_| {
+            let current = *c.next();
+
+            assert_eq!((current - previous).abs(), 1);
+            previous = current;
+        });
+    }
 }
@@ -1,19 +1,28 @@
 mod app;
+mod curve;
 mod iter_utils;
 mod midi_event;
 mod midi_mapper;
+mod monitor;
+mod note_ledger;
+mod panic;
 mod parser;
 mod pipeline;
+mod scale;
 mod scheduler;
+mod send;
 mod tempo;
 mod transforms;
 
 use crate::app::AppConfig;
 use app::App;
 use clap::{Parser, Subcommand};
+use midi_event::MIDIEventIdentity;
 use midi_mapper::MidiMapper;
+use monitor::{Monitor, MonitorOptions};
 use parser::test_parse;
 use schemars::schema_for;
+use send::{MessageSender, SendMessage};
 
 /// TODO
 #[derive(Debug, Parser)]
@@ -33,6 +42,29 @@ enum Commands {
     },
     Devices,
     Schema,
+    /// Prints a decoded stream of the events coming from the input devices
+    Monitor {
+        /// Only listen to input devices starting with this name, can be repeated. Listens to all devices if omitted
+        #[arg(short, long)]
+        device: Vec<String>,
+        /// Only print events of this type, can be repeated
+        #[arg(short, long, value_enum)]
+        event_type: Vec<MIDIEventIdentity>,
+        /// Only print events on this channel (1-16), can be repeated
+        #[arg(short, long, value_parser = clap::value_parser!(u8).range(1..=16))]
+        channel: Vec<u8>,
+        /// Disable colored output
+        #[arg(long)]
+        no_color: bool,
+    },
+    /// Sends a message straight to an output device
+    Send {
+        /// Output device name, as listed by `devices`
+        #[arg(short, long)]
+        device: String,
+        #[command(subcommand)]
+        message: SendMessage,
+    },
 }
 
 // How many threads should I use here...?
@@ -45,25 +77,47 @@ async fn main() {
             let config = test_parse(config_file).unwrap();
             let mut midi_mapper = MidiMapper::new();
 
-            config.input_devices.iter().for_each(|(alias, full_name)| {
-                midi_mapper.add_input(full_name.clone(), alias.clone());
+            config.input_devices.iter().for_each(|(alias, device)| {
+                midi_mapper.add_input(device.name(), alias.clone(), device.aggregate_options());
             });
 
-            config.output_devices.iter().for_each(|(alias, full_name)| {
-                midi_mapper.add_output(full_name.clone(), alias.clone());
+            config.output_devices.iter().for_each(|(alias, device)| {
+                midi_mapper.add_output(device.name(), alias.clone(), device.note_off_encoding());
             });
 
+            let panic_options = config.panic.clone().unwrap_or_default();
             let app = App::from_config(config);
-            midi_mapper.start(app);
+            midi_mapper.start(app, panic_options);
         }
 
-        Commands::Devices {} => {
+        Commands::Devices => {
             MidiMapper::print_ports();
         }
 
-        Commands::Schema {} => {
+        Commands::Schema => {
             let schema = schema_for!(AppConfig);
             println!("{}", serde_json::to_string_pretty(&schema).unwrap());
         }
+
+        Commands::Monitor {
+            device,
+            event_type,
+            channel,
+            no_color,
+        } => {
+            let mut monitor = Monitor::new(MonitorOptions {
+                devices: device,
+                event_types: event_type,
+                channels: channel,
+                color: !no_color,
+            });
+
+            monitor.start();
+            tokio::signal::ctrl_c().await.unwrap();
+        }
+
+        Commands::Send { device, message } => {
+            MessageSender::new(device).run(message).await;
+        }
     }
 }
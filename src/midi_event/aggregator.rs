@@ -0,0 +1,178 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::{Controller, Controller14, MIDIEvent, ParameterNumber};
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct AggregateOptions {
+    /// Controllers (0-31) sent as MSB/LSB pairs, combined into `Controller14` events. The MSB goes
+    /// through right away along with the last LSB (the controller 32 numbers above), and the LSB
+    /// then refines the value
+    pub fourteen_bit_cc: Option<Vec<u8>>,
+    /// Combines NRPN (CC 99/98) and RPN (CC 101/100) sequences followed by data entry (CC 6/38)
+    /// into `Nrpn` and `Rpn` events. Data entry MSBs and LSBs each go through the same way
+    pub parameter_numbers: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum ParameterKind {
+    Nrpn,
+    Rpn,
+}
+
+#[derive(Debug, Default, Clone)]
+struct ChannelState {
+    // Last MSB and LSB received for each 14-bit controller
+    msb: [Option<u8>; 32],
+    lsb: [u8; 32],
+    // Parameter being selected, with its MSB and LSB
+    parameter: Option<(ParameterKind, Option<u8>, Option<u8>)>,
+    // Last data entry MSB and LSB for the selected parameter
+    data_msb: Option<u8>,
+    data_lsb: u8,
+}
+
+impl ChannelState {
+    fn selected_parameter(&self) -> Option<(ParameterKind, u16)> {
+        match self.parameter {
+            Some((kind, Some(msb), Some(lsb))) => Some((kind, (msb as u16) << 7 | lsb as u16)),
+            _ => None,
+        }
+    }
+
+    fn select(&mut self, kind: ParameterKind, msb: Option<u8>, lsb: Option<u8>) {
+        let (current_msb, current_lsb) = match self.parameter {
+            Some((current, msb, lsb)) if current == kind => (msb, lsb),
+            _ => (None, None),
+        };
+
+        self.parameter = Some((kind, msb.or(current_msb), lsb.or(current_lsb)));
+        self.data_msb = None;
+        self.data_lsb = 0;
+
+        // 127/127 is the null parameter, used to stop data entry from changing anything
+        if self.selected_parameter() == Some((kind, 0x3FFF)) {
+            self.parameter = None;
+        }
+    }
+}
+
+fn parameter_number(
+    kind: ParameterKind,
+    channel: u8,
+    parameter: u16,
+    msb: u8,
+    lsb: u8,
+) -> MIDIEvent {
+    let parameter = ParameterNumber {
+        channel,
+        parameter,
+        value: (msb as u16) << 7 | lsb as u16,
+    };
+
+    match kind {
+        ParameterKind::Nrpn => MIDIEvent::Nrpn(parameter),
+        ParameterKind::Rpn => MIDIEvent::Rpn(parameter),
+    }
+}
+
+/// Combines the controller sequences of a single input device into 14-bit controller and
+/// parameter number events
+pub struct Aggregator {
+    fourteen_bit_cc: Vec<u8>,
+    parameter_numbers: bool,
+    channels: [ChannelState; 16],
+}
+
+impl Aggregator {
+    pub fn from_config(options: AggregateOptions) -> Self {
+        Self {
+            fourteen_bit_cc: options
+                .fourteen_bit_cc
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|controller| *controller < 32)
+                .collect(),
+            parameter_numbers: options.parameter_numbers.unwrap_or(false),
+            channels: Default::default(),
+        }
+    }
+
+    /// Returns the event to forward, if any. Parameter selection is swallowed, as is an LSB
+    /// coming in before any MSB
+    pub fn aggregate(&mut self, event: MIDIEvent) -> Option<MIDIEvent> {
+        let MIDIEvent::Controller(Controller {
+            channel,
+            controller,
+            value,
+        }) = event
+        else {
+            return Some(event);
+        };
+        let state = &mut self.channels[channel as usize & 0x0F];
+
+        if self.parameter_numbers {
+            match controller {
+                // Parameter selection never goes through on its own
+                98..=101 => {
+                    let kind = match controller {
+                        99 | 98 => ParameterKind::Nrpn,
+                        _ => ParameterKind::Rpn,
+                    };
+
+                    match controller {
+                        99 | 101 => state.select(kind, Some(value), None),
+                        _ => state.select(kind, None, Some(value)),
+                    }
+
+                    return None;
+                }
+                6 => {
+                    if let Some((kind, parameter)) = state.selected_parameter() {
+                        state.data_msb = Some(value);
+
+                        return Some(parameter_number(
+                            kind,
+                            channel,
+                            parameter,
+                            value,
+                            state.data_lsb,
+                        ));
+                    }
+                }
+                38 => {
+                    if let Some((kind, parameter)) = state.selected_parameter() {
+                        state.data_lsb = value;
+                        let msb = state.data_msb?;
+
+                        return Some(parameter_number(kind, channel, parameter, msb, value));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if self.fourteen_bit_cc.contains(&controller) {
+            state.msb[controller as usize] = Some(value);
+
+            return Some(MIDIEvent::Controller14(Controller14 {
+                channel,
+                controller,
+                value: (value as u16) << 7 | state.lsb[controller as usize] as u16,
+            }));
+        }
+
+        if controller >= 32 && self.fourteen_bit_cc.contains(&(controller - 32)) {
+            state.lsb[controller as usize - 32] = value;
+            let msb = state.msb[controller as usize - 32]?;
+
+            return Some(MIDIEvent::Controller14(Controller14 {
+                channel,
+                controller: controller - 32,
+                value: (msb as u16) << 7 | value as u16,
+            }));
+        }
+
+        Some(event)
+    }
+}
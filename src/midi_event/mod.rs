@@ -1,10 +1,13 @@
 // Based on: https://github.com/derekdreery/nom-midi-rs/
+mod aggregator;
 #[cfg(test)]
 mod tests;
 mod types;
 mod utils;
 
-pub use self::types::Note;
+pub use self::aggregator::{AggregateOptions, Aggregator};
+pub use self::types::{Note, PitchClass};
+use clap::ValueEnum;
 use nom::number::streaming::be_u8;
 use nom::{
     error::{make_error, ErrorKind},
@@ -19,6 +22,8 @@ pub trait ToMidi {
     }
 }
 
+/// Wraps an event as if the router sent it, for tests
+#[cfg(test)]
 pub trait Wrap {
     fn wrap(self) -> MIDIRouterEvent
     where
@@ -35,20 +40,37 @@ pub struct NoteEvent {
     pub velocity: u8,
 }
 
+// Note ons and offs share the same entity, what tells them apart is the MIDIEvent variant they're
+// wrapped in. On its own, a NoteEvent is encoded as a note on.
 impl ToMidi for NoteEvent {
     fn to_midi(&self) -> Vec<u8> {
-        // We treat note events internally as the same entity
-        // The only difference is the velocity of the note, where
-        // 0 is equivalent to a note off.
-        // Is this a bad idea? makes interfaces easier.
-        if self.velocity == 0 {
-            vec![0x80 + self.channel, self.note, self.velocity]
-        } else {
-            vec![0x90 + self.channel, self.note, self.velocity]
+        vec![0x90 + self.channel, self.note, self.velocity]
+    }
+}
+
+/// How note offs are encoded when sent to an output device
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Deserialize, JsonSchema)]
+pub enum NoteOffEncoding {
+    /// A note off (0x80) keeping the release velocity
+    KeepVelocity,
+    /// A note off (0x80) with a velocity of 0, for devices that misbehave with release velocity
+    #[default]
+    ZeroVelocity,
+    /// A note on (0x90) with a velocity of 0
+    NoteOn,
+}
+
+impl NoteOffEncoding {
+    pub fn encode(&self, note: &NoteEvent) -> Vec<u8> {
+        match self {
+            Self::KeepVelocity => vec![0x80 + note.channel, note.note, note.velocity],
+            Self::ZeroVelocity => vec![0x80 + note.channel, note.note, 0],
+            Self::NoteOn => vec![0x90 + note.channel, note.note, 0],
         }
     }
 }
 
+#[cfg(test)]
 impl Wrap for NoteEvent {
     fn wrap(self) -> MIDIRouterEvent {
         MIDIRouterEvent {
@@ -62,6 +84,18 @@ impl Wrap for NoteEvent {
     }
 }
 
+/// A note on, or a note off when the velocity is 0, on the first channel, for tests
+#[cfg(test)]
+pub fn note_event(note: u8, velocity: u8) -> MIDIEvent {
+    NoteEvent {
+        channel: 0,
+        note,
+        velocity,
+    }
+    .wrap()
+    .event
+}
+
 impl NoteEvent {
     pub fn get_note_off(&self) -> NoteEvent {
         NoteEvent {
@@ -73,9 +107,9 @@ impl NoteEvent {
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct PolyphonicPressure {
-    channel: u8,
-    note: u8,
-    pressure: u8,
+    pub channel: u8,
+    pub note: u8,
+    pub pressure: u8,
 }
 
 impl ToMidi for PolyphonicPressure {
@@ -99,8 +133,8 @@ impl ToMidi for Controller {
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct ProgramChange {
-    channel: u8,
-    program: u8,
+    pub channel: u8,
+    pub program: u8,
 }
 
 impl ToMidi for ProgramChange {
@@ -111,8 +145,8 @@ impl ToMidi for ProgramChange {
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct ChannelPressure {
-    channel: u8,
-    pressure: u8,
+    pub channel: u8,
+    pub pressure: u8,
 }
 
 impl ToMidi for ChannelPressure {
@@ -123,17 +157,116 @@ impl ToMidi for ChannelPressure {
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct PitchBend {
-    channel: u8,
+    pub channel: u8,
     lsb: u8,
     msb: u8,
 }
 
+impl PitchBend {
+    /// Builds a pitch bend from a signed value (-8192 to 8191), clamping it if needed
+    pub fn new(channel: u8, value: i16) -> Self {
+        let raw = (value.clamp(-8192, 8191) + 8192) as u16;
+
+        Self {
+            channel,
+            lsb: (raw & 0x7F) as u8,
+            msb: (raw >> 7) as u8,
+        }
+    }
+
+    /// The bend amount as a signed value, where 0 is the center position
+    pub fn value(&self) -> i16 {
+        (((self.msb as i16) << 7) | self.lsb as i16) - 8192
+    }
+
+    /// Builds a pitch bend from a value between -1.0 and 1.0, clamping it if needed
+    pub fn from_normalized(channel: u8, value: f64) -> Self {
+        let value = value.clamp(-1.0, 1.0);
+        let scale = if value < 0.0 { 8192.0 } else { 8191.0 };
+
+        Self::new(channel, (value * scale).round() as i16)
+    }
+
+    /// The bend amount between -1.0 and 1.0, where 0.0 is the center position
+    pub fn normalized(&self) -> f64 {
+        let value = self.value() as f64;
+
+        if value < 0.0 {
+            value / 8192.0
+        } else {
+            value / 8191.0
+        }
+    }
+
+    pub fn set_normalized(&mut self, value: f64) {
+        *self = Self::from_normalized(self.channel, value);
+    }
+}
+
 impl ToMidi for PitchBend {
     fn to_midi(&self) -> Vec<u8> {
         vec![0xE0 + self.channel, self.lsb, self.msb]
     }
 }
 
+/// A controller (0-31) whose value is split between its MSB and the controller 32 numbers above
+/// carrying its LSB
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Controller14 {
+    pub channel: u8,
+    pub controller: u8,
+    pub value: u16,
+}
+
+impl Controller14 {
+    fn to_midi_messages(&self) -> Vec<Vec<u8>> {
+        controller_messages(
+            self.channel,
+            &[
+                (self.controller, (self.value >> 7) as u8 & 0x7F),
+                (self.controller + 32, self.value as u8 & 0x7F),
+            ],
+        )
+    }
+}
+
+/// A registered or non-registered parameter number, along with its 14-bit value
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParameterNumber {
+    pub channel: u8,
+    pub parameter: u16,
+    pub value: u16,
+}
+
+impl ParameterNumber {
+    /// Selects the parameter through the given MSB/LSB controllers, then sets it with data entry
+    fn to_midi_messages(&self, msb_controller: u8, lsb_controller: u8) -> Vec<Vec<u8>> {
+        controller_messages(
+            self.channel,
+            &[
+                (msb_controller, (self.parameter >> 7) as u8 & 0x7F),
+                (lsb_controller, self.parameter as u8 & 0x7F),
+                (6, (self.value >> 7) as u8 & 0x7F),
+                (38, self.value as u8 & 0x7F),
+            ],
+        )
+    }
+}
+
+fn controller_messages(channel: u8, controllers: &[(u8, u8)]) -> Vec<Vec<u8>> {
+    controllers
+        .iter()
+        .map(|(controller, value)| {
+            Controller {
+                channel,
+                controller: *controller,
+                value: *value,
+            }
+            .to_midi()
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct MIDIRouterEvent {
     pub device: String,
@@ -155,9 +288,12 @@ pub enum MIDIEvent {
     ChannelPressure(ChannelPressure),
     ProgramChange(ProgramChange),
     PitchBend(PitchBend),
+    Controller14(Controller14),
+    Nrpn(ParameterNumber),
+    Rpn(ParameterNumber),
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, JsonSchema, ValueEnum)]
 pub enum MIDIEventIdentity {
     NoteOff,
     NoteOn,
@@ -166,6 +302,9 @@ pub enum MIDIEventIdentity {
     ChannelPressure,
     ProgramChange,
     PitchBend,
+    Controller14,
+    Nrpn,
+    Rpn,
 }
 
 impl MIDIEvent {
@@ -178,6 +317,9 @@ impl MIDIEvent {
             MIDIEvent::ChannelPressure(_) => MIDIEventIdentity::ChannelPressure,
             MIDIEvent::ProgramChange(_) => MIDIEventIdentity::ProgramChange,
             MIDIEvent::PitchBend(_) => MIDIEventIdentity::PitchBend,
+            MIDIEvent::Controller14(_) => MIDIEventIdentity::Controller14,
+            MIDIEvent::Nrpn(_) => MIDIEventIdentity::Nrpn,
+            MIDIEvent::Rpn(_) => MIDIEventIdentity::Rpn,
         }
     }
 
@@ -190,6 +332,8 @@ impl MIDIEvent {
             Self::ChannelPressure(v) => v.channel,
             Self::ProgramChange(v) => v.channel,
             Self::PitchBend(v) => v.channel,
+            Self::Controller14(v) => v.channel,
+            Self::Nrpn(v) | Self::Rpn(v) => v.channel,
         }
     }
 
@@ -202,6 +346,8 @@ impl MIDIEvent {
             Self::ChannelPressure(v) => v.channel = new_channel,
             Self::ProgramChange(v) => v.channel = new_channel,
             Self::PitchBend(v) => v.channel = new_channel,
+            Self::Controller14(v) => v.channel = new_channel,
+            Self::Nrpn(v) | Self::Rpn(v) => v.channel = new_channel,
         };
     }
 }
@@ -220,20 +366,38 @@ impl TryFrom<&[u8]> for MIDIEvent {
 impl ToMidi for MIDIEvent {
     fn to_midi(&self) -> Vec<u8> {
         match self {
-            MIDIEvent::NoteOff(v) => (&NoteEvent {
-                // TODO: should maybe make this an option? some devices send NoteOffs with velocity
-                // Which may be undesired if the target device doesn't know how to respond to it
-                velocity: 0,
-                channel: v.channel,
-                note: v.note,
-            })
-                .to_midi(),
+            MIDIEvent::NoteOff(v) => NoteOffEncoding::KeepVelocity.encode(v),
             MIDIEvent::NoteOn(v) => v.to_midi(),
             MIDIEvent::PolyphonicPressure(v) => v.to_midi(),
             MIDIEvent::Controller(v) => v.to_midi(),
             MIDIEvent::ChannelPressure(v) => v.to_midi(),
             MIDIEvent::ProgramChange(v) => v.to_midi(),
             MIDIEvent::PitchBend(v) => v.to_midi(),
+            MIDIEvent::Controller14(_) | MIDIEvent::Nrpn(_) | MIDIEvent::Rpn(_) => self
+                .to_midi_messages(NoteOffEncoding::KeepVelocity)
+                .concat(),
+        }
+    }
+}
+
+impl MIDIEvent {
+    /// Same as `to_midi`, but encoding note offs the way the target device expects them
+    pub fn to_midi_with(&self, note_off_encoding: NoteOffEncoding) -> Vec<u8> {
+        match self {
+            MIDIEvent::NoteOff(v) => note_off_encoding.encode(v),
+            _ => self.to_midi(),
+        }
+    }
+
+    /// The messages to send for this event, with note offs encoded as the target device expects.
+    /// 14-bit controllers and parameter numbers take a sequence of controllers, everything else
+    /// is a single message
+    pub fn to_midi_messages(&self, note_off_encoding: NoteOffEncoding) -> Vec<Vec<u8>> {
+        match self {
+            MIDIEvent::Controller14(v) => v.to_midi_messages(),
+            MIDIEvent::Nrpn(v) => v.to_midi_messages(99, 98),
+            MIDIEvent::Rpn(v) => v.to_midi_messages(101, 100),
+            _ => vec![self.to_midi_with(note_off_encoding)],
         }
     }
 }
@@ -260,11 +424,18 @@ pub fn parse_midi_event(i: &[u8]) -> IResult<&[u8], MIDIEvent> {
             let (i, note_code) = utils::be_u7(i)?;
             let (_i, velocity) = utils::be_u7(i)?;
 
-            MIDIEvent::NoteOn(NoteEvent {
+            let note = NoteEvent {
                 channel,
                 note: note_code,
                 velocity,
-            })
+            };
+
+            // A note on with no velocity is just another way of sending a note off
+            if velocity == 0 {
+                MIDIEvent::NoteOff(note)
+            } else {
+                MIDIEvent::NoteOn(note)
+            }
         }
 
         0xA => {
@@ -1,6 +1,10 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
-    use crate::midi_event::{utils::compare_u8_slices, MIDIEvent, Note, NoteEvent, ToMidi};
+    use crate::midi_event::{
+        utils::compare_u8_slices, AggregateOptions, Aggregator, Controller, Controller14,
+        MIDIEvent, Note, NoteEvent, NoteOffEncoding, ParameterNumber, PitchBend, ToMidi,
+    };
     use std::cmp;
 
     #[test]
@@ -33,9 +37,10 @@ mod tests {
 
     #[test]
     fn to_midi() {
-        let cases: [&[u8]; 3] = [
-            &[144, 70, 43],  // Ch0, NoteOff, As4, 0
+        let cases: [&[u8]; 4] = [
+            &[144, 70, 43],  // Ch0, NoteOn, As4, 43
             &[128, 70, 0],   // Ch0, NoteOff, As4, 0
+            &[128, 70, 64],  // Ch0, NoteOff, As4, 64
             &[176, 59, 127], // Ch0, Controller, 59, 127
         ];
 
@@ -48,4 +53,211 @@ mod tests {
             );
         })
     }
+
+    #[test]
+    fn note_names() {
+        assert_eq!(Note::C4.to_string(), "C4");
+        assert_eq!(Note::Fs1n.to_string(), "F#-1");
+        assert_eq!(Note::G9.to_string(), "G9");
+
+        assert_eq!("C4".parse::<Note>(), Ok(Note::C4));
+        assert_eq!("c#4".parse::<Note>(), Ok(Note::Cs4));
+        assert_eq!("Db4".parse::<Note>(), Ok(Note::Cs4));
+        assert_eq!("Cb4".parse::<Note>(), Ok(Note::B3));
+        assert_eq!("C-1".parse::<Note>(), Ok(Note::C1n));
+        assert_eq!("60".parse::<Note>(), Ok(Note::C4));
+
+        assert!("H4".parse::<Note>().is_err());
+        assert!("G#9".parse::<Note>().is_err());
+        assert!("128".parse::<Note>().is_err());
+    }
+
+    #[test]
+    fn note_names_in_config() {
+        assert_eq!(serde_yaml::from_str::<Note>("C4").unwrap(), Note::C4);
+        assert_eq!(serde_yaml::from_str::<Note>("60").unwrap(), Note::C4);
+
+        let error = serde_yaml::from_str::<Note>("H4").unwrap_err();
+        assert!(error.to_string().contains("Invalid note name: 'H4'"));
+        assert!(serde_yaml::from_str::<Note>("128").is_err());
+    }
+
+    #[test]
+    fn note_on_without_velocity_is_a_note_off() {
+        let message: &[u8] = &[145, 60, 0]; // Ch1, NoteOn, C4, 0
+
+        assert_eq!(
+            MIDIEvent::try_from(message).unwrap(),
+            MIDIEvent::NoteOff(NoteEvent {
+                channel: 1,
+                note: Note::C4.into(),
+                velocity: 0
+            })
+        );
+    }
+
+    #[test]
+    fn note_off_encodings() {
+        let note_off = MIDIEvent::NoteOff(NoteEvent {
+            channel: 2,
+            note: Note::C4.into(),
+            velocity: 64,
+        });
+
+        assert_eq!(
+            note_off.to_midi_with(NoteOffEncoding::KeepVelocity),
+            vec![130, 60, 64]
+        );
+        assert_eq!(
+            note_off.to_midi_with(NoteOffEncoding::ZeroVelocity),
+            vec![130, 60, 0]
+        );
+        assert_eq!(
+            note_off.to_midi_with(NoteOffEncoding::NoteOn),
+            vec![146, 60, 0]
+        );
+    }
+
+    fn cc(controller: u8, value: u8) -> MIDIEvent {
+        MIDIEvent::Controller(Controller {
+            channel: 2,
+            controller,
+            value,
+        })
+    }
+
+    #[test]
+    fn aggregates_fourteen_bit_controllers() {
+        let mut aggregator = Aggregator::from_config(AggregateOptions {
+            fourteen_bit_cc: Some(vec![1]),
+            parameter_numbers: None,
+        });
+        let controller = |value| {
+            MIDIEvent::Controller14(Controller14 {
+                channel: 2,
+                controller: 1,
+                value,
+            })
+        };
+
+        assert_eq!(
+            aggregator.aggregate(cc(1, 0x40)),
+            Some(controller(0x40 << 7))
+        );
+        assert_eq!(aggregator.aggregate(cc(33, 0x01)), Some(controller(0x2001)));
+        assert_eq!(aggregator.aggregate(cc(2, 5)), Some(cc(2, 5)));
+        assert_eq!(aggregator.aggregate(cc(99, 5)), Some(cc(99, 5)));
+
+        // The MSB goes through with the last LSB, without waiting for the next one
+        assert_eq!(
+            aggregator.aggregate(cc(1, 0x41)),
+            Some(controller(0x41 << 7 | 1))
+        );
+
+        // An LSB on its own fine tunes the last MSB
+        assert_eq!(
+            aggregator.aggregate(cc(33, 0x02)),
+            Some(controller(0x41 << 7 | 2))
+        );
+    }
+
+    #[test]
+    fn sends_lone_msbs_right_away() {
+        let mut aggregator = Aggregator::from_config(AggregateOptions {
+            fourteen_bit_cc: Some(vec![7, 8]),
+            parameter_numbers: None,
+        });
+
+        // Followed by silence, the MSB still goes out
+        assert_eq!(
+            aggregator.aggregate(cc(7, 100)),
+            Some(MIDIEvent::Controller14(Controller14 {
+                channel: 2,
+                controller: 7,
+                value: 100 << 7,
+            }))
+        );
+
+        // An LSB before any MSB has nothing to refine
+        assert_eq!(aggregator.aggregate(cc(40, 3)), None);
+        assert_eq!(
+            aggregator.aggregate(cc(8, 1)),
+            Some(MIDIEvent::Controller14(Controller14 {
+                channel: 2,
+                controller: 8,
+                value: 1 << 7 | 3,
+            }))
+        );
+    }
+
+    #[test]
+    fn aggregates_parameter_numbers() {
+        let mut aggregator = Aggregator::from_config(AggregateOptions {
+            fourteen_bit_cc: None,
+            parameter_numbers: Some(true),
+        });
+        let nrpn = |value| {
+            MIDIEvent::Nrpn(ParameterNumber {
+                channel: 2,
+                parameter: 0x0105,
+                value,
+            })
+        };
+
+        assert_eq!(aggregator.aggregate(cc(99, 2)), None);
+        assert_eq!(aggregator.aggregate(cc(98, 5)), None);
+        assert_eq!(aggregator.aggregate(cc(6, 10)), Some(nrpn(10 << 7)));
+        assert_eq!(aggregator.aggregate(cc(38, 3)), Some(nrpn(10 << 7 | 3)));
+
+        // Data entry keeps the last LSB until a new one comes in
+        assert_eq!(aggregator.aggregate(cc(6, 11)), Some(nrpn(11 << 7 | 3)));
+        assert_eq!(aggregator.aggregate(cc(7, 90)), Some(cc(7, 90)));
+
+        // The null RPN stops data entry from being aggregated
+        assert_eq!(aggregator.aggregate(cc(101, 127)), None);
+        assert_eq!(aggregator.aggregate(cc(100, 127)), None);
+        assert_eq!(aggregator.aggregate(cc(6, 10)), Some(cc(6, 10)));
+    }
+
+    #[test]
+    fn serializes_parameter_numbers() {
+        let rpn = MIDIEvent::Rpn(ParameterNumber {
+            channel: 1,
+            parameter: 0,
+            value: 12 << 7,
+        });
+
+        assert_eq!(
+            rpn.to_midi_messages(NoteOffEncoding::default()),
+            vec![
+                vec![0xB1, 101, 0],
+                vec![0xB1, 100, 0],
+                vec![0xB1, 6, 12],
+                vec![0xB1, 38, 0],
+            ]
+        );
+
+        let controller = MIDIEvent::Controller14(Controller14 {
+            channel: 0,
+            controller: 7,
+            value: 0x3FFF,
+        });
+        assert_eq!(controller.to_midi(), vec![0xB0, 7, 127, 0xB0, 39, 127]);
+    }
+
+    #[test]
+    fn pitch_bend_values() {
+        let mut bend = PitchBend::new(0, -8192);
+        assert_eq!(bend.to_midi(), vec![0xE0, 0, 0]);
+        assert_eq!(bend.normalized(), -1.0);
+
+        assert_eq!(PitchBend::new(0, 0).to_midi(), vec![0xE0, 0, 64]);
+
+        bend.set_normalized(1.0);
+        assert_eq!(bend.value(), 8191);
+        assert_eq!(bend.to_midi(), vec![0xE0, 127, 127]);
+
+        assert_eq!(PitchBend::from_normalized(3, -0.5).value(), -4096);
+        assert_eq!(PitchBend::new(0, 20000).value(), 8191);
+    }
 }
@@ -1,4 +1,11 @@
-use std::mem;
+use std::{fmt, mem, str::FromStr};
+
+use schemars::{
+  gen::SchemaGenerator,
+  schema::{InstanceType, Metadata, Schema, SchemaObject},
+  JsonSchema,
+};
+use serde::{de, Deserialize, Deserializer};
 
 /// A note representable in a 7 bit unsigned int. The subscript 's' to a note means sharp. The
 /// subscript 'n' to an octave means negate, so `Cs2n` = C# in octave -2.
@@ -9,7 +16,7 @@ use std::mem;
 /// This implements both From<u8>, Into<u8>, From<i8> and Into<i8> so the names can be completely
 /// ignored if prefered
 #[repr(u8)]
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
 pub enum Note {
   C1n = 0x00,
   Cs1n = 0x01,
@@ -155,9 +162,176 @@ impl std::ops::Add<i8> for Note {
   }
 }
 
+const PITCH_CLASS_NAMES: [&str; 12] = [
+  "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+impl Note {
+  /// Octave of the note, where middle C (`C4`) is in octave 4
+  pub fn octave(&self) -> i8 {
+    (*self as u8 / 12) as i8 - 1
+  }
+
+  /// Position of the note within its octave, 0 being C and 11 being B
+  pub fn pitch_class(&self) -> u8 {
+    *self as u8 % 12
+  }
+}
+
+/// Parses a note name such as `C`, `D#` or `Eb`, returning its offset in semitones from C (which
+/// can fall outside of the octave, e.g. `Cb`) and whatever is left of the input
+fn parse_note_name(name: &str) -> Option<(i8, &str)> {
+  let mut chars = name.chars();
+  let base: i8 = match chars.next()?.to_ascii_uppercase() {
+    'C' => 0,
+    'D' => 2,
+    'E' => 4,
+    'F' => 5,
+    'G' => 7,
+    'A' => 9,
+    'B' => 11,
+    _ => return None,
+  };
+
+  let rest = chars.as_str();
+  let (accidental, rest) = match rest.chars().next() {
+    Some('#') | Some('s') => (1, &rest[1..]),
+    Some('b') => (-1, &rest[1..]),
+    _ => (0, rest),
+  };
+
+  Some((base + accidental, rest))
+}
+
+/// A note without its octave, such as `C`, `D#` or `Eb`
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct PitchClass(u8);
+
+impl From<PitchClass> for u8 {
+  /// Position of the pitch class within the octave, from 0 for `C` to 11 for `B`
+  fn from(pitch_class: PitchClass) -> u8 {
+    pitch_class.0
+  }
+}
+
+impl FromStr for PitchClass {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match parse_note_name(s.trim()) {
+      Some((offset, "")) => Ok(PitchClass(offset.rem_euclid(12) as u8)),
+      _ => Err(format!("Invalid pitch class: '{s}'")),
+    }
+  }
+}
+
+impl<'de> Deserialize<'de> for PitchClass {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    String::deserialize(deserializer)?
+      .parse()
+      .map_err(de::Error::custom)
+  }
+}
+
+impl JsonSchema for PitchClass {
+  fn schema_name() -> String {
+    "PitchClass".to_string()
+  }
+
+  fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+      metadata: Some(Box::new(Metadata {
+        description: Some("A note name without its octave, such as `C`, `D#` or `Eb`".to_string()),
+        ..Default::default()
+      })),
+      instance_type: Some(InstanceType::String.into()),
+      ..Default::default()
+    }
+    .into()
+  }
+}
+
+/// Parses either a note name with its octave (`C4`, `F#-1`, `Db3`) or a raw note number (`60`)
+impl FromStr for Note {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    if let Ok(number) = s.parse::<u8>() {
+      return match number {
+        0..=127 => Ok(number.into()),
+        _ => Err(format!("Note number out of range: '{s}'")),
+      };
+    }
+
+    let (offset, octave) =
+      parse_note_name(s).ok_or_else(|| format!("Invalid note name: '{s}'"))?;
+    let octave: i16 = octave
+      .parse()
+      .map_err(|_| format!("Invalid octave in note: '{s}'"))?;
+
+    match (octave + 1) * 12 + offset as i16 {
+      number @ 0..=127 => Ok((number as u8).into()),
+      _ => Err(format!("Note out of range: '{s}'")),
+    }
+  }
+}
+
+/// Notes in the config are written the same way, so a bad one fails when the config is loaded
+impl<'de> Deserialize<'de> for Note {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct NoteVisitor;
+
+    impl<'de> de::Visitor<'de> for NoteVisitor {
+      type Value = Note;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a note name such as `C4` or a note number")
+      }
+
+      fn visit_str<E: de::Error>(self, v: &str) -> Result<Note, E> {
+        v.parse().map_err(E::custom)
+      }
+
+      fn visit_u64<E: de::Error>(self, v: u64) -> Result<Note, E> {
+        self.visit_str(&v.to_string())
+      }
+
+      fn visit_i64<E: de::Error>(self, v: i64) -> Result<Note, E> {
+        self.visit_str(&v.to_string())
+      }
+    }
+
+    deserializer.deserialize_any(NoteVisitor)
+  }
+}
+
+impl JsonSchema for Note {
+  fn schema_name() -> String {
+    "Note".to_string()
+  }
+
+  fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+      metadata: Some(Box::new(Metadata {
+        description: Some("A note name such as `C4` or `F#-1`, or a note number".to_string()),
+        ..Default::default()
+      })),
+      instance_type: Some(vec![InstanceType::String, InstanceType::Integer].into()),
+      ..Default::default()
+    }
+    .into()
+  }
+}
+
+/// Displays the scientific pitch name of the note, e.g. `C4` or `F#-1`
 impl std::fmt::Display for Note {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    write!(f, "{:?}", *self as u8)
+    write!(
+      f,
+      "{}{}",
+      PITCH_CLASS_NAMES[self.pitch_class() as usize],
+      self.octave()
+    )
   }
 }
 
@@ -1,3 +1,4 @@
+#[cfg(test)]
 use std::cmp;
 
 use nom::{
@@ -6,6 +7,7 @@ use nom::{
 };
 
 /// Compares two slices to check for equality
+#[cfg(test)]
 pub fn compare_u8_slices(a: &[u8], b: &[u8]) -> cmp::Ordering {
   a.iter()
     .zip(b)
@@ -3,17 +3,34 @@ use midir::{
 };
 
 use crate::{
-    midi_event::{MIDIEvent, MIDIRouterEvent, ToMidi},
+    app::EgressMessage,
+    midi_event::{
+        AggregateOptions, Aggregator, Controller, MIDIEvent, MIDIRouterEvent, NoteOffEncoding,
+        ToMidi,
+    },
+    note_ledger::{NoteLedger, NoteLedgerHandle},
+    panic::PanicOptions,
     App,
 };
 use std::{collections::HashMap, error::Error};
 
+enum ControlMessage {
+    Panic,
+    Shutdown,
+}
+
+struct Output {
+    connection: MidiOutputConnection,
+    note_off_encoding: NoteOffEncoding,
+}
+
 pub struct MidiMapper {
     midi_sender: flume::Sender<MIDIRouterEvent>,
     ingress: flume::Receiver<MIDIRouterEvent>,
 
     input_connections: HashMap<String, MidiInputConnection<()>>,
-    output_connections: HashMap<String, MidiOutputConnection>,
+    output_connections: HashMap<String, Output>,
+    note_ledger: NoteLedgerHandle,
 }
 
 impl MidiMapper {
@@ -25,33 +42,128 @@ impl MidiMapper {
             ingress: rx,
             input_connections: HashMap::new(),
             output_connections: HashMap::new(),
+            note_ledger: NoteLedger::new_handle(),
         }
     }
 
-    pub fn start(&mut self, mut app: App) {
-        let (egress_sender, egress_receiver) = flume::unbounded::<MIDIRouterEvent>();
+    pub fn start(&mut self, mut app: App, panic_options: PanicOptions) {
+        let (egress_sender, egress_receiver) = flume::unbounded::<EgressMessage>();
+        let (app_ingress_sender, app_ingress) = flume::unbounded::<MIDIRouterEvent>();
+        let (control_sender, control_receiver) = flume::unbounded::<ControlMessage>();
 
         app.set_egress(egress_sender);
-        app.set_ingress(self.ingress.clone());
+        app.set_ingress(app_ingress);
+        app.set_note_ledger(self.note_ledger.clone());
 
         tokio::spawn(async {
             app.run().await;
         });
 
+        // Intercept the panic trigger before it reaches any pipeline
+        let ingress = self.ingress.clone();
+        let trigger = panic_options.trigger.clone();
+        let panic_sender = control_sender.clone();
+        tokio::spawn(async move {
+            while let Ok(message) = ingress.recv_async().await {
+                match &trigger {
+                    Some(trigger) if trigger.matches(&message) => {
+                        panic_sender.send(ControlMessage::Panic).unwrap();
+                    }
+                    _ => app_ingress_sender.send(message).unwrap(),
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            tokio::signal::ctrl_c().await.unwrap();
+            control_sender.send(ControlMessage::Shutdown).unwrap();
+        });
+
         loop {
-            let message = egress_receiver.recv().unwrap();
+            let control = flume::Selector::new()
+                .recv(&egress_receiver, |message| {
+                    self.route(message.unwrap());
+                    None
+                })
+                .recv(&control_receiver, |control| control.ok())
+                .wait();
 
-            match self.output_connections.get_mut(&message.device) {
-                Some(output) => {
-                    let midi_message = message.event.to_midi();
-                    output.send(&midi_message).unwrap();
+            match control {
+                Some(ControlMessage::Panic) => {
+                    println!("Panic! Releasing all notes");
+                    self.panic(&panic_options);
                 }
-                None => todo!(),
-            };
-            //println!("{message}");
+
+                Some(ControlMessage::Shutdown) => {
+                    println!("Shutting down");
+
+                    // Let whatever the pipelines already emitted go out before silencing everything
+                    egress_receiver
+                        .try_iter()
+                        .for_each(|message| self.route(message));
+                    self.panic(&panic_options);
+
+                    return;
+                }
+
+                None => {}
+            }
         }
     }
 
+    fn route(&mut self, egress_message: EgressMessage) {
+        let message = self
+            .note_ledger
+            .lock()
+            .unwrap()
+            .track(egress_message.pipeline, egress_message.message);
+
+        self.send(message);
+    }
+
+    fn send(&mut self, message: MIDIRouterEvent) {
+        match self.output_connections.get_mut(&message.device) {
+            Some(output) => {
+                message
+                    .event
+                    .to_midi_messages(output.note_off_encoding)
+                    .iter()
+                    .for_each(|midi_message| output.connection.send(midi_message).unwrap());
+            }
+            None => todo!(),
+        };
+        //println!("{message}");
+    }
+
+    /// Releases every note we've sent on, optionally followed by All Notes/Sound Off
+    fn panic(&mut self, options: &PanicOptions) {
+        let released = self.note_ledger.lock().unwrap().release_all();
+
+        released.into_iter().for_each(|message| self.send(message));
+
+        let controllers = [
+            (options.all_notes_off.unwrap_or(false), 123),
+            (options.all_sound_off.unwrap_or(false), 120),
+        ];
+
+        controllers
+            .into_iter()
+            .filter(|(enabled, _)| *enabled)
+            .for_each(|(_, controller)| {
+                self.output_connections.values_mut().for_each(|output| {
+                    (0..16).for_each(|channel| {
+                        let message = MIDIEvent::Controller(Controller {
+                            channel,
+                            controller,
+                            value: 0,
+                        });
+
+                        output.connection.send(&message.to_midi()).unwrap();
+                    })
+                })
+            });
+    }
+
     pub fn print_ports() {
         let midi_in = MidiInput::new("midir forwarding input").unwrap();
         let midi_out = MidiOutput::new("midir forwarding output").unwrap();
@@ -66,7 +178,7 @@ impl MidiMapper {
         println!("Available input ports:");
         print_ports_from(midi_in);
 
-        println!("");
+        println!();
 
         println!("Available output ports:");
         print_ports_from(midi_out);
@@ -80,15 +192,21 @@ impl MidiMapper {
         let port = midi_ports
             .iter()
             .find(|port| midi_io.port_name(port).unwrap().starts_with(&name))
-            .expect(format!("Could not find port: '{name}'").as_str());
+            .unwrap_or_else(|| panic!("Could not find port: '{name}'"));
 
         Ok(port.clone())
     }
 
-    pub fn add_input(&mut self, device_name: String, alias: String) {
+    pub fn add_input(
+        &mut self,
+        device_name: String,
+        alias: String,
+        aggregate_options: Option<AggregateOptions>,
+    ) {
         self.input_connections.insert(
             alias.clone(),
-            self.connect_input(device_name, alias).unwrap(),
+            self.connect_input(device_name, alias, aggregate_options)
+                .unwrap(),
         );
     }
 
@@ -96,11 +214,13 @@ impl MidiMapper {
         &self,
         name: String,
         alias: String,
+        aggregate_options: Option<AggregateOptions>,
     ) -> Result<MidiInputConnection<()>, ConnectError<MidiInput>> {
         let midi_in = MidiInput::new("midir forwarding input").unwrap();
 
         let port = Self::select_port_by_name(&midi_in, name).unwrap();
         let local_tx = self.midi_sender.clone();
+        let mut aggregator = aggregate_options.map(Aggregator::from_config);
 
         midi_in.connect(
             &port,
@@ -108,6 +228,14 @@ impl MidiMapper {
             move |_stamp, message, _| {
                 let midi_event = MIDIEvent::try_from(message).expect("Could not parse midi event!");
 
+                let midi_event = match &mut aggregator {
+                    Some(aggregator) => match aggregator.aggregate(midi_event) {
+                        Some(midi_event) => midi_event,
+                        None => return,
+                    },
+                    None => midi_event,
+                };
+
                 local_tx
                     .send(MIDIRouterEvent {
                         device: alias.clone(),
@@ -119,15 +247,22 @@ impl MidiMapper {
         )
     }
 
-    pub fn add_output(&mut self, device_name: String, alias: String) {
-        self.output_connections
-            .insert(alias, self.connect_output(device_name).unwrap());
+    pub fn add_output(
+        &mut self,
+        device_name: String,
+        alias: String,
+        note_off_encoding: NoteOffEncoding,
+    ) {
+        self.output_connections.insert(
+            alias,
+            Output {
+                connection: Self::connect_output(device_name).unwrap(),
+                note_off_encoding,
+            },
+        );
     }
 
-    fn connect_output(
-        &self,
-        name: String,
-    ) -> Result<MidiOutputConnection, ConnectError<MidiOutput>> {
+    pub fn connect_output(name: String) -> Result<MidiOutputConnection, ConnectError<MidiOutput>> {
         let midi_out = MidiOutput::new("midir forwarding output").unwrap();
         let port = Self::select_port_by_name(&midi_out, name).unwrap();
 
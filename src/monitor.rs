@@ -0,0 +1,307 @@
+use midir::{Ignore, MidiInput, MidiInputConnection};
+
+use crate::midi_event::{MIDIEvent, MIDIEventIdentity, Note};
+
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+
+pub struct MonitorOptions {
+    /// Only listen to ports whose names start with any of these. Empty means all ports
+    pub devices: Vec<String>,
+    pub event_types: Vec<MIDIEventIdentity>,
+    /// 1-based channels, as shown to the user
+    pub channels: Vec<u8>,
+    pub color: bool,
+}
+
+// Prints a decoded stream of every event coming from the input ports
+pub struct Monitor {
+    options: MonitorOptions,
+    connections: Vec<MidiInputConnection<()>>,
+}
+
+impl Monitor {
+    pub fn new(options: MonitorOptions) -> Self {
+        Self {
+            options,
+            connections: vec![],
+        }
+    }
+
+    pub fn start(&mut self) {
+        let midi_in = MidiInput::new("midori monitor").unwrap();
+
+        let port_names: Vec<String> = midi_in
+            .ports()
+            .iter()
+            .filter_map(|port| midi_in.port_name(port).ok())
+            .filter(|name| {
+                self.options.devices.is_empty()
+                    || self
+                        .options
+                        .devices
+                        .iter()
+                        .any(|device| name.starts_with(device))
+            })
+            .collect();
+
+        if port_names.is_empty() {
+            println!("No matching input ports found");
+            return;
+        }
+
+        port_names.into_iter().for_each(|name| {
+            println!("Monitoring '{name}'");
+            self.connect(name);
+        });
+    }
+
+    fn connect(&mut self, name: String) {
+        let mut midi_in = MidiInput::new("midori monitor").unwrap();
+        // Let SysEx through, but not the clock and active sensing spam
+        midi_in.ignore(Ignore::TimeAndActiveSense);
+
+        let port = midi_in
+            .ports()
+            .into_iter()
+            .find(|port| {
+                midi_in
+                    .port_name(port)
+                    .is_ok_and(|port_name| port_name == name)
+            })
+            .unwrap_or_else(|| panic!("Could not find port: '{name}'"));
+
+        let formatter = Formatter {
+            event_types: self.options.event_types.clone(),
+            channels: self.options.channels.clone(),
+            color: self.options.color,
+        };
+
+        let connection = midi_in
+            .connect(
+                &port,
+                "midori monitor",
+                move |stamp, message, _| {
+                    if let Some(line) = formatter.format(stamp, &name, message) {
+                        println!("{line}");
+                    }
+                },
+                (),
+            )
+            .unwrap();
+
+        self.connections.push(connection);
+    }
+}
+
+struct Formatter {
+    event_types: Vec<MIDIEventIdentity>,
+    channels: Vec<u8>,
+    color: bool,
+}
+
+impl Formatter {
+    fn format(&self, stamp: u64, device: &str, message: &[u8]) -> Option<String> {
+        let timestamp = format!("{:>10.3}", stamp as f64 / 1_000_000.0);
+
+        let (color, kind, channel, details) = match MIDIEvent::try_from(message) {
+            Ok(event) => {
+                if !self.should_show(&event) {
+                    return None;
+                }
+
+                let channel = format!("ch {:<2}", event.get_channel() + 1);
+                let (color, details) = describe_event(&event);
+
+                (
+                    color,
+                    format!("{:?}", event.get_identity()),
+                    channel,
+                    details,
+                )
+            }
+
+            // Nothing but sysex and system messages should end up here
+            Err(_) if !self.event_types.is_empty() || !self.channels.is_empty() => return None,
+            Err(_) if message.first() == Some(&0xF0) => {
+                ("\x1b[95m", "SysEx".into(), "".into(), to_hex(message))
+            }
+            Err(_) => ("\x1b[37m", "Unknown".into(), "".into(), to_hex(message)),
+        };
+
+        Some(format!(
+            "{}  {:<24} {:<6} {}  {}",
+            self.paint(DIM, &timestamp),
+            device,
+            channel,
+            self.paint(color, &format!("{kind:<18}")),
+            details
+        ))
+    }
+
+    fn should_show(&self, event: &MIDIEvent) -> bool {
+        (self.event_types.is_empty() || self.event_types.contains(&event.get_identity()))
+            && (self.channels.is_empty() || self.channels.contains(&(event.get_channel() + 1)))
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{color}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Returns the color and a human readable description of the event's contents
+fn describe_event(event: &MIDIEvent) -> (&'static str, String) {
+    match event {
+        MIDIEvent::NoteOn(v) => (
+            "\x1b[92m",
+            format!(
+                "{:<4} ({:>3})  vel {}",
+                Note::from(v.note),
+                v.note,
+                v.velocity
+            ),
+        ),
+        MIDIEvent::NoteOff(v) => (
+            "\x1b[32m",
+            format!(
+                "{:<4} ({:>3})  vel {}",
+                Note::from(v.note),
+                v.note,
+                v.velocity
+            ),
+        ),
+        MIDIEvent::PolyphonicPressure(v) => (
+            "\x1b[36m",
+            format!("{:<4} ({:>3})  {}", Note::from(v.note), v.note, v.pressure),
+        ),
+        MIDIEvent::Controller(v) => (
+            "\x1b[93m",
+            format!(
+                "CC {:<3} {:<18} {}",
+                v.controller,
+                controller_name(v.controller).unwrap_or(""),
+                v.value
+            ),
+        ),
+        MIDIEvent::ChannelPressure(v) => ("\x1b[96m", format!("{}", v.pressure)),
+        MIDIEvent::ProgramChange(v) => ("\x1b[94m", format!("{}", v.program)),
+        MIDIEvent::PitchBend(v) => ("\x1b[35m", format!("{:+}", v.value())),
+        MIDIEvent::Controller14(v) => (
+            "\x1b[93m",
+            format!(
+                "CC {:<3} {:<18} {}",
+                v.controller,
+                controller_name(v.controller).unwrap_or(""),
+                v.value
+            ),
+        ),
+        MIDIEvent::Nrpn(v) => ("\x1b[33m", format!("NRPN {:<5} {}", v.parameter, v.value)),
+        MIDIEvent::Rpn(v) => ("\x1b[33m", format!("RPN {:<6} {}", v.parameter, v.value)),
+    }
+}
+
+/// Names for the most commonly used controllers
+fn controller_name(controller: u8) -> Option<&'static str> {
+    let name = match controller {
+        0 => "Bank Select",
+        1 => "Modulation",
+        2 => "Breath",
+        4 => "Foot",
+        5 => "Portamento Time",
+        6 => "Data Entry",
+        7 => "Volume",
+        8 => "Balance",
+        10 => "Pan",
+        11 => "Expression",
+        32 => "Bank Select LSB",
+        38 => "Data Entry LSB",
+        64 => "Sustain",
+        65 => "Portamento",
+        66 => "Sostenuto",
+        67 => "Soft Pedal",
+        71 => "Resonance",
+        72 => "Release Time",
+        73 => "Attack Time",
+        74 => "Cutoff",
+        98 => "NRPN LSB",
+        99 => "NRPN MSB",
+        100 => "RPN LSB",
+        101 => "RPN MSB",
+        120 => "All Sound Off",
+        121 => "Reset Controllers",
+        123 => "All Notes Off",
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+fn to_hex(message: &[u8]) -> String {
+    message
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Formatter;
+    use crate::midi_event::MIDIEventIdentity;
+
+    fn get_formatter(event_types: Vec<MIDIEventIdentity>, channels: Vec<u8>) -> Formatter {
+        Formatter {
+            event_types,
+            channels,
+            color: false,
+        }
+    }
+
+    #[test]
+    fn decodes_notes() {
+        let line = get_formatter(vec![], vec![])
+            .format(1_500_000, "op1", &[0x90, 60, 100])
+            .unwrap();
+
+        assert!(line.contains("1.500"));
+        assert!(line.contains("ch 1 "));
+        assert!(line.contains("NoteOn"));
+        assert!(line.contains("C4"));
+        assert!(line.contains("vel 100"));
+    }
+
+    #[test]
+    fn decodes_controllers_and_pitch_bend() {
+        let formatter = get_formatter(vec![], vec![]);
+
+        let cc = formatter.format(0, "op1", &[0xB3, 64, 127]).unwrap();
+        assert!(cc.contains("ch 4 "));
+        assert!(cc.contains("Sustain"));
+
+        let bend = formatter.format(0, "op1", &[0xE0, 0, 0]).unwrap();
+        assert!(bend.contains("-8192"));
+    }
+
+    #[test]
+    fn prints_sysex_as_hex() {
+        let line = get_formatter(vec![], vec![])
+            .format(0, "op1", &[0xF0, 0x7E, 0x7F, 0xF7])
+            .unwrap();
+
+        assert!(line.contains("F0 7E 7F F7"));
+    }
+
+    #[test]
+    fn filters_by_type_and_channel() {
+        let formatter = get_formatter(vec![MIDIEventIdentity::NoteOn], vec![2]);
+
+        assert!(formatter.format(0, "op1", &[0x91, 60, 100]).is_some());
+        assert!(formatter.format(0, "op1", &[0x90, 60, 100]).is_none());
+        assert!(formatter.format(0, "op1", &[0xB1, 1, 100]).is_none());
+        assert!(formatter.format(0, "op1", &[0xF0, 0x7E, 0xF7]).is_none());
+    }
+}
@@ -0,0 +1,172 @@
+use std::sync::{Arc, Mutex};
+
+use crate::midi_event::{MIDIEvent, MIDIRouterEvent, NoteEvent};
+
+pub type NoteLedgerHandle = Arc<Mutex<NoteLedger>>;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SoundingNote {
+    /// Position of the pipeline that sent the note on
+    pub pipeline: usize,
+    pub device: String,
+    pub channel: u8,
+    pub note: u8,
+}
+
+/// Keeps track of the notes currently sounding on each output device and channel, so note offs
+/// end up wherever their note on went, and everything can be silenced on demand
+#[derive(Debug, Default)]
+pub struct NoteLedger {
+    // In the order they were played
+    notes: Vec<SoundingNote>,
+}
+
+impl NoteLedger {
+    pub fn new_handle() -> NoteLedgerHandle {
+        Arc::new(Mutex::new(Self::default()))
+    }
+
+    /// Records an outgoing message. Note offs are rerouted to the device and channel of the note on
+    /// they belong to, in case a transform sent them somewhere else in the meantime
+    pub fn track(&mut self, pipeline: usize, mut message: MIDIRouterEvent) -> MIDIRouterEvent {
+        match &mut message.event {
+            MIDIEvent::NoteOn(note) if note.velocity > 0 => {
+                self.notes.push(SoundingNote {
+                    pipeline,
+                    device: message.device.clone(),
+                    channel: note.channel,
+                    note: note.note,
+                });
+            }
+
+            MIDIEvent::NoteOn(note) | MIDIEvent::NoteOff(note) => {
+                let same_note = |sounding: &SoundingNote| {
+                    sounding.pipeline == pipeline && sounding.note == note.note
+                };
+
+                // Prefer an exact match, otherwise take the oldest note on that only had its channel
+                // or its device changed. Anything further away is a stray note off, not ours
+                let position = self
+                    .notes
+                    .iter()
+                    .position(|sounding| {
+                        same_note(sounding)
+                            && sounding.device == message.device
+                            && sounding.channel == note.channel
+                    })
+                    .or_else(|| {
+                        self.notes.iter().position(|sounding| {
+                            same_note(sounding) && sounding.device == message.device
+                        })
+                    })
+                    .or_else(|| {
+                        self.notes.iter().position(|sounding| {
+                            same_note(sounding) && sounding.channel == note.channel
+                        })
+                    });
+
+                if let Some(position) = position {
+                    let sounding = self.notes.remove(position);
+
+                    message.device = sounding.device;
+                    note.channel = sounding.channel;
+                }
+            }
+
+            _ => {}
+        }
+
+        message
+    }
+
+    pub fn sounding(&self) -> &[SoundingNote] {
+        &self.notes
+    }
+
+    /// Empties the ledger, returning the note offs needed to silence every sounding note
+    pub fn release_all(&mut self) -> Vec<MIDIRouterEvent> {
+        self.notes
+            .drain(..)
+            .map(|sounding| MIDIRouterEvent {
+                device: sounding.device,
+                event: MIDIEvent::NoteOff(NoteEvent {
+                    channel: sounding.channel,
+                    note: sounding.note,
+                    velocity: 0,
+                }),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NoteLedger;
+    use crate::midi_event::{MIDIEvent, MIDIRouterEvent, NoteEvent};
+
+    fn note(device: &str, channel: u8, note: u8, velocity: u8) -> MIDIRouterEvent {
+        let note = NoteEvent {
+            channel,
+            note,
+            velocity,
+        };
+
+        MIDIRouterEvent {
+            device: device.into(),
+            event: if velocity == 0 {
+                MIDIEvent::NoteOff(note)
+            } else {
+                MIDIEvent::NoteOn(note)
+            },
+        }
+    }
+
+    #[test]
+    fn releases_sounding_notes() {
+        let mut ledger = NoteLedger::default();
+
+        ledger.track(0, note("emc", 0, 60, 100));
+        ledger.track(0, note("emc", 1, 62, 100));
+        ledger.track(0, note("op1", 0, 60, 100));
+        ledger.track(0, note("emc", 0, 60, 0));
+
+        assert_eq!(ledger.sounding().len(), 2);
+        assert_eq!(
+            ledger.release_all(),
+            vec![note("emc", 1, 62, 0), note("op1", 0, 60, 0)]
+        );
+        assert!(ledger.release_all().is_empty());
+    }
+
+    #[test]
+    fn routes_note_offs_to_their_note_on() {
+        let mut ledger = NoteLedger::default();
+
+        ledger.track(0, note("emc", 2, 60, 100));
+        ledger.track(1, note("op1", 3, 60, 100));
+
+        // The channel mapping changed while the note was held
+        assert_eq!(
+            ledger.track(0, note("emc", 5, 60, 0)),
+            note("emc", 2, 60, 0)
+        );
+        assert_eq!(
+            ledger.track(1, note("emc", 3, 60, 0)),
+            note("op1", 3, 60, 0)
+        );
+
+        // Stray note offs on another device and channel leave sounding notes alone
+        ledger.track(0, note("op1", 4, 62, 100));
+        assert_eq!(
+            ledger.track(0, note("emc", 7, 62, 0)),
+            note("emc", 7, 62, 0)
+        );
+        assert_eq!(ledger.sounding().len(), 1);
+
+        // Unknown note offs go through untouched
+        assert_eq!(
+            ledger.track(0, note("emc", 5, 60, 0)),
+            note("emc", 5, 60, 0)
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::midi_event::{MIDIEvent, MIDIRouterEvent};
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct PanicOptions {
+    /// Besides releasing every sounding note, also send All Notes Off (CC 123) on every channel
+    pub all_notes_off: Option<bool>,
+    /// Also send All Sound Off (CC 120) on every channel
+    pub all_sound_off: Option<bool>,
+    /// An incoming controller that triggers a panic while running
+    pub trigger: Option<PanicTrigger>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct PanicTrigger {
+    pub device: Option<String>,
+    /// 0-based channel (0-15) the controller must come in on. Triggers on any channel if omitted
+    #[schemars(range(max = 15))]
+    pub channel: Option<u8>,
+    pub controller: u8,
+    /// Only trigger on this value. Triggers on any value if omitted
+    pub value: Option<u8>,
+}
+
+impl PanicTrigger {
+    pub fn matches(&self, message: &MIDIRouterEvent) -> bool {
+        let MIDIEvent::Controller(controller) = &message.event else {
+            return false;
+        };
+
+        controller.controller == self.controller
+            && self.value.is_none_or(|value| controller.value == value)
+            && self
+                .channel
+                .is_none_or(|channel| controller.channel == channel)
+            && self
+                .device
+                .as_ref()
+                .is_none_or(|device| &message.device == device)
+    }
+}
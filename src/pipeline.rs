@@ -5,12 +5,16 @@ use serde::Deserialize;
 use std::pin::Pin;
 
 use crate::app::MIDIMapperEvent;
+use crate::note_ledger::NoteLedgerHandle;
 use crate::scheduler::Scheduler;
 use crate::tempo::ClockHandler;
 use crate::transforms::transform::SerializedTransform;
 use crate::transforms::{
-    ArpeggioTransform, DistributeTransform, FilterTransform, InspectTransform, MapTransform,
-    MirrorTransform, OutputTransform, Transform, WasmTransform,
+    ArpeggioTransform, BendTransform, ChordTransform, ConvertTransform, DelayTransform,
+    DistributeTransform, EuclidTransform, FilterTransform, InspectTransform, MapTransform,
+    MirrorTransform, OutputTransform, QuantizeTransform, RandomTransform, RatchetTransform,
+    SequencerTransform, SplitTransform, StrumTransform, Transform, TransposeTransform,
+    VelocityTransform, WasmTransform,
 };
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -30,6 +34,7 @@ impl Pipeline {
     pub fn pipe_stream(
         origin_stream: Pin<Box<dyn Stream<Item = MIDIMapperEvent> + Send>>,
         clock: &ClockHandler,
+        note_ledger: &NoteLedgerHandle,
         mut transform: Box<dyn Transform + Send>,
     ) -> Pin<Box<dyn Stream<Item = MIDIMapperEvent> + Send>> {
         let mut streams: Vec<Pin<Box<dyn Stream<Item = MIDIMapperEvent> + Send>>> =
@@ -44,6 +49,7 @@ impl Pipeline {
         let (scheduler, scheduler_handler) = Scheduler::new();
 
         transform.set_scheduler(scheduler_handler.clone());
+        transform.set_note_ledger(note_ledger.clone());
 
         let stream = futures::stream::select_all::select_all(streams).filter_map(move |v| {
             let result = transform
@@ -102,6 +108,58 @@ impl Pipeline {
                         SerializedTransform::Mirror(config) => {
                             Box::new(MirrorTransform::from_config(config))
                         }
+
+                        SerializedTransform::Transpose(config) => {
+                            Box::new(TransposeTransform::from_config(config))
+                        }
+
+                        SerializedTransform::Quantize(config) => {
+                            Box::new(QuantizeTransform::from_config(config))
+                        }
+
+                        SerializedTransform::Chord(config) => {
+                            Box::new(ChordTransform::from_config(config))
+                        }
+
+                        SerializedTransform::Velocity(config) => {
+                            Box::new(VelocityTransform::from_config(config))
+                        }
+
+                        SerializedTransform::Split(config) => {
+                            Box::new(SplitTransform::from_config(config))
+                        }
+
+                        SerializedTransform::Convert(config) => {
+                            Box::new(ConvertTransform::from_config(config))
+                        }
+
+                        SerializedTransform::Bend(config) => {
+                            Box::new(BendTransform::from_config(config))
+                        }
+
+                        SerializedTransform::Sequencer(config) => {
+                            Box::new(SequencerTransform::from_config(config))
+                        }
+
+                        SerializedTransform::Euclid(config) => {
+                            Box::new(EuclidTransform::from_config(config))
+                        }
+
+                        SerializedTransform::Random(config) => {
+                            Box::new(RandomTransform::from_config(config))
+                        }
+
+                        SerializedTransform::Delay(config) => {
+                            Box::new(DelayTransform::from_config(config))
+                        }
+
+                        SerializedTransform::Ratchet(config) => {
+                            Box::new(RatchetTransform::from_config(config))
+                        }
+
+                        SerializedTransform::Strum(config) => {
+                            Box::new(StrumTransform::from_config(config))
+                        }
                     };
 
                     transform
@@ -110,7 +168,11 @@ impl Pipeline {
         }
     }
 
-    pub async fn listen(self, clock: ClockHandler) -> impl Stream<Item = MIDIMapperEvent> {
+    pub async fn listen(
+        self,
+        clock: ClockHandler,
+        note_ledger: NoteLedgerHandle,
+    ) -> impl Stream<Item = MIDIMapperEvent> {
         let name = self.name.clone();
         let origin_stream: Pin<Box<dyn Stream<Item = MIDIMapperEvent> + Send>> =
             Box::pin(self.rx.into_stream());
@@ -119,7 +181,7 @@ impl Pipeline {
         self.transforms
             .into_iter()
             .fold(origin_stream, move |acc, transform| {
-                Self::pipe_stream(acc, &clock, transform)
+                Self::pipe_stream(acc, &clock, &note_ledger, transform)
             })
     }
 }
@@ -0,0 +1,196 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::midi_event::PitchClass;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub enum ScaleMode {
+    Major,
+    Minor,
+    Ionian,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Aeolian,
+    Locrian,
+    HarmonicMinor,
+    MelodicMinor,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+    WholeTone,
+    Chromatic,
+}
+
+impl ScaleMode {
+    /// Semitones from the root for every degree of the scale
+    pub fn intervals(&self) -> Vec<u8> {
+        match self {
+            Self::Major | Self::Ionian => vec![0, 2, 4, 5, 7, 9, 11],
+            Self::Minor | Self::Aeolian => vec![0, 2, 3, 5, 7, 8, 10],
+            Self::Dorian => vec![0, 2, 3, 5, 7, 9, 10],
+            Self::Phrygian => vec![0, 1, 3, 5, 7, 8, 10],
+            Self::Lydian => vec![0, 2, 4, 6, 7, 9, 11],
+            Self::Mixolydian => vec![0, 2, 4, 5, 7, 9, 10],
+            Self::Locrian => vec![0, 1, 3, 5, 6, 8, 10],
+            Self::HarmonicMinor => vec![0, 2, 3, 5, 7, 8, 11],
+            Self::MelodicMinor => vec![0, 2, 3, 5, 7, 9, 11],
+            Self::MajorPentatonic => vec![0, 2, 4, 7, 9],
+            Self::MinorPentatonic => vec![0, 3, 5, 7, 10],
+            Self::Blues => vec![0, 3, 5, 6, 7, 10],
+            Self::WholeTone => vec![0, 2, 4, 6, 8, 10],
+            Self::Chromatic => (0..12).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct ScaleOptions {
+    /// Root note of the scale, e.g. `D#` or `Bb`
+    pub root: PitchClass,
+    /// Defaults to `Major`
+    pub mode: Option<ScaleMode>,
+    /// Semitones from the root making up the scale, e.g. `[0, 3, 5, 7, 10]`. Takes precedence over `mode`
+    pub pitch_classes: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+pub enum Rounding {
+    /// The closest note in the scale, going down on ties
+    #[default]
+    Nearest,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scale {
+    // Every MIDI note that belongs to the scale, in ascending order
+    notes: Vec<u8>,
+}
+
+impl Scale {
+    pub fn from_config(options: ScaleOptions) -> Self {
+        let intervals = options
+            .pitch_classes
+            .unwrap_or_else(|| options.mode.unwrap_or(ScaleMode::Major).intervals());
+
+        Self::new(options.root.into(), &intervals)
+    }
+
+    pub fn new(root: u8, intervals: &[u8]) -> Self {
+        let pitch_classes: Vec<u8> = intervals.iter().map(|i| (root + i % 12) % 12).collect();
+
+        Self {
+            notes: (0..=127)
+                .filter(|note| pitch_classes.contains(&(note % 12)))
+                .collect(),
+        }
+    }
+
+    pub fn contains(&self, note: u8) -> bool {
+        self.notes.binary_search(&note).is_ok()
+    }
+
+    /// Moves the note to one that belongs to the scale
+    pub fn snap(&self, note: u8, rounding: Rounding) -> Option<u8> {
+        let position = match self.notes.binary_search(&note) {
+            Ok(_) => return Some(note),
+            // Position of the first note above
+            Err(position) => position,
+        };
+
+        let above = self.notes.get(position).copied();
+        let below = position
+            .checked_sub(1)
+            .and_then(|position| self.notes.get(position).copied());
+
+        match rounding {
+            Rounding::Up => above,
+            Rounding::Down => below,
+            Rounding::Nearest => match (below, above) {
+                (Some(below), Some(above)) if above - note < note - below => Some(above),
+                (Some(below), _) => Some(below),
+                (None, above) => above,
+            },
+        }
+    }
+
+    /// Moves a note belonging to the scale by a number of scale degrees
+    pub fn step(&self, note: u8, degrees: i16) -> Option<u8> {
+        let position = self.notes.binary_search(&note).ok()? as i16 + degrees;
+
+        usize::try_from(position)
+            .ok()
+            .and_then(|position| self.notes.get(position).copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Rounding, Scale, ScaleMode, ScaleOptions};
+
+    fn d_dorian() -> Scale {
+        Scale::from_config(ScaleOptions {
+            root: "D".parse().unwrap(),
+            mode: Some(ScaleMode::Dorian),
+            pitch_classes: None,
+        })
+    }
+
+    #[test]
+    fn builds_from_root_and_mode() {
+        let scale = d_dorian();
+
+        // D dorian has the same notes as C major
+        assert!([60, 62, 64, 65, 67, 69, 71]
+            .iter()
+            .all(|n| scale.contains(*n)));
+        assert!([61, 63, 66, 68, 70].iter().all(|n| !scale.contains(*n)));
+
+        let custom = Scale::from_config(ScaleOptions {
+            root: "F#".parse().unwrap(),
+            mode: None,
+            pitch_classes: Some(vec![0, 7]),
+        });
+
+        assert!(custom.contains(66) && custom.contains(61));
+        assert!(!custom.contains(60));
+    }
+
+    #[test]
+    fn snaps_notes() {
+        let scale = d_dorian();
+
+        assert_eq!(scale.snap(60, Rounding::Nearest), Some(60));
+        assert_eq!(scale.snap(61, Rounding::Nearest), Some(60));
+        assert_eq!(scale.snap(61, Rounding::Up), Some(62));
+        assert_eq!(scale.snap(66, Rounding::Down), Some(65));
+
+        let fifths = Scale::new(0, &[0, 7]);
+        assert_eq!(fifths.snap(66, Rounding::Nearest), Some(67));
+        assert_eq!(fifths.snap(66, Rounding::Down), Some(60));
+        assert_eq!(Scale::new(0, &[0]).snap(121, Rounding::Up), None);
+    }
+
+    #[test]
+    fn steps_through_degrees() {
+        let scale = d_dorian();
+
+        assert_eq!(scale.step(60, 2), Some(64));
+        assert_eq!(scale.step(60, -1), Some(59));
+        assert_eq!(scale.step(61, 1), None);
+        assert_eq!(scale.step(0, -1), None);
+    }
+
+    #[test]
+    fn rejects_unknown_roots() {
+        let options = serde_yaml::from_str::<ScaleOptions>("root: Bb").unwrap();
+        assert_eq!(u8::from(options.root), 10);
+
+        let error = serde_yaml::from_str::<ScaleOptions>("root: H").unwrap_err();
+        assert!(error.to_string().contains("Invalid pitch class: 'H'"));
+        assert!(serde_yaml::from_str::<ScaleOptions>("root: C4").is_err());
+    }
+}
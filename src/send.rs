@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use clap::Subcommand;
+use midir::MidiOutputConnection;
+
+use crate::{
+    midi_event::{Controller, MIDIEvent, Note, NoteEvent, ProgramChange, ToMidi},
+    midi_mapper::MidiMapper,
+};
+
+#[derive(Debug, Subcommand)]
+pub enum SendMessage {
+    /// Sends a note on
+    NoteOn {
+        #[arg(value_parser = clap::value_parser!(u8).range(1..=16))]
+        channel: u8,
+        /// Note name (e.g. C4, F#2) or number
+        note: Note,
+        #[arg(default_value_t = 100, value_parser = clap::value_parser!(u8).range(0..=127))]
+        velocity: u8,
+    },
+    /// Sends a note off
+    NoteOff {
+        #[arg(value_parser = clap::value_parser!(u8).range(1..=16))]
+        channel: u8,
+        /// Note name (e.g. C4, F#2) or number
+        note: Note,
+        #[arg(default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=127))]
+        velocity: u8,
+    },
+    /// Sends a note on, followed by its note off after the given duration
+    Note {
+        #[arg(value_parser = clap::value_parser!(u8).range(1..=16))]
+        channel: u8,
+        /// Note name (e.g. C4, F#2) or number
+        note: Note,
+        #[arg(default_value_t = 100, value_parser = clap::value_parser!(u8).range(0..=127))]
+        velocity: u8,
+        /// How long the note is held, in milliseconds
+        #[arg(short, long, default_value_t = 500)]
+        duration: u64,
+    },
+    /// Sends a control change
+    Cc {
+        #[arg(value_parser = clap::value_parser!(u8).range(1..=16))]
+        channel: u8,
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=127))]
+        controller: u8,
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=127))]
+        value: u8,
+    },
+    /// Sweeps a controller from one value to another
+    CcSweep {
+        #[arg(value_parser = clap::value_parser!(u8).range(1..=16))]
+        channel: u8,
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=127))]
+        controller: u8,
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=127))]
+        from: u8,
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=127))]
+        to: u8,
+        /// How long the sweep takes, in milliseconds
+        #[arg(short, long, default_value_t = 1000)]
+        duration: u64,
+    },
+    /// Sends a program change
+    ProgramChange {
+        #[arg(value_parser = clap::value_parser!(u8).range(1..=16))]
+        channel: u8,
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=127))]
+        program: u8,
+    },
+    /// Sends all notes off (CC 123) to the given channel, or to every channel if omitted
+    AllNotesOff {
+        #[arg(value_parser = clap::value_parser!(u8).range(1..=16))]
+        channel: Option<u8>,
+    },
+    /// Sends raw bytes written in hex, e.g. `90 3C 64`
+    Raw {
+        #[arg(required = true, value_parser = parse_hex_byte)]
+        bytes: Vec<u8>,
+    },
+}
+
+fn parse_hex_byte(s: &str) -> Result<u8, String> {
+    u8::from_str_radix(s.trim_start_matches("0x"), 16).map_err(|_| format!("Invalid byte: '{s}'"))
+}
+
+// Sends messages straight to an output device, without going through any pipeline
+pub struct MessageSender {
+    output: MidiOutputConnection,
+}
+
+impl MessageSender {
+    pub fn new(device_name: String) -> Self {
+        Self {
+            output: MidiMapper::connect_output(device_name).unwrap(),
+        }
+    }
+
+    fn send(&mut self, event: MIDIEvent) {
+        self.output.send(&event.to_midi()).unwrap();
+    }
+
+    pub async fn run(&mut self, message: SendMessage) {
+        match message {
+            SendMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => self.send(MIDIEvent::NoteOn(NoteEvent {
+                channel: channel - 1,
+                note: note.into(),
+                velocity,
+            })),
+
+            SendMessage::NoteOff {
+                channel,
+                note,
+                velocity,
+            } => self.send(MIDIEvent::NoteOff(NoteEvent {
+                channel: channel - 1,
+                note: note.into(),
+                velocity,
+            })),
+
+            SendMessage::Note {
+                channel,
+                note,
+                velocity,
+                duration,
+            } => {
+                let note_on = NoteEvent {
+                    channel: channel - 1,
+                    note: note.into(),
+                    velocity,
+                };
+                let note_off = note_on.get_note_off();
+
+                self.send(MIDIEvent::NoteOn(note_on));
+                tokio::time::sleep(Duration::from_millis(duration)).await;
+                self.send(MIDIEvent::NoteOff(note_off));
+            }
+
+            SendMessage::Cc {
+                channel,
+                controller,
+                value,
+            } => self.send(MIDIEvent::Controller(Controller {
+                channel: channel - 1,
+                controller,
+                value,
+            })),
+
+            SendMessage::CcSweep {
+                channel,
+                controller,
+                from,
+                to,
+                duration,
+            } => {
+                let steps = from.abs_diff(to) as u64;
+                let values: Vec<u8> = if from <= to {
+                    (from..=to).collect()
+                } else {
+                    (to..=from).rev().collect()
+                };
+
+                for (i, value) in values.into_iter().enumerate() {
+                    if i > 0 {
+                        tokio::time::sleep(Duration::from_millis(duration / steps)).await;
+                    }
+
+                    self.send(MIDIEvent::Controller(Controller {
+                        channel: channel - 1,
+                        controller,
+                        value,
+                    }));
+                }
+            }
+
+            SendMessage::ProgramChange { channel, program } => {
+                self.send(MIDIEvent::ProgramChange(ProgramChange {
+                    channel: channel - 1,
+                    program,
+                }))
+            }
+
+            SendMessage::AllNotesOff { channel } => {
+                let channels = match channel {
+                    Some(channel) => vec![channel - 1],
+                    None => (0..16).collect(),
+                };
+
+                channels.into_iter().for_each(|channel| {
+                    self.send(MIDIEvent::Controller(Controller {
+                        channel,
+                        controller: 123,
+                        value: 0,
+                    }))
+                });
+            }
+
+            SendMessage::Raw { bytes } => {
+                self.output.send(&bytes).unwrap();
+            }
+        }
+
+        // Give the backend a chance to flush before the connection is dropped
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
@@ -1,10 +1,13 @@
 use futures::{Stream, StreamExt};
 use std::future;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::broadcast::Sender;
 use tokio::time::Interval;
 use tokio_stream::wrappers::BroadcastStream;
 
+/// Clock pulses per quarter note (beat)
+pub const PPQN: f64 = 96.0;
+
 pub struct Clock {
     bpm: f64,
     // Pulses per quarter note (beat)
@@ -78,6 +81,7 @@ pub struct ClockHandler {
     bpm_sender: flume::Sender<f64>,
 }
 impl ClockHandler {
+    #[allow(dead_code)]
     pub fn set_bpm(&self, bpm: f64) {
         self.bpm_sender.send(bpm).unwrap();
     }
@@ -88,6 +92,35 @@ impl ClockHandler {
     }
 }
 
+/// Measures how long the steps of a clock subdivision last, from the time between their ticks
+#[derive(Debug, Default)]
+pub struct StepTimer {
+    last_tick: Option<Instant>,
+    step: Option<Duration>,
+}
+
+impl StepTimer {
+    /// To be called on every tick of the subdivision
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+
+        if let Some(last_tick) = self.last_tick {
+            self.step = Some(now - last_tick);
+        }
+        self.last_tick = Some(now);
+    }
+
+    /// Length of the last step, known from the second tick on
+    pub fn step(&self) -> Option<Duration> {
+        self.step
+    }
+
+    #[cfg(test)]
+    pub fn set_step(&mut self, step: Duration) {
+        self.step = Some(step);
+    }
+}
+
 pub fn every<I>(s: impl Stream<Item = I>, n: u64) -> impl Stream<Item = I> {
     let mut count = 0;
     let max = n - 1;
@@ -1,37 +1,295 @@
 use super::Transform;
 use crate::{
     iter_utils::{Cycle, CycleDirection},
-    midi_event::{MIDIEvent, MIDIRouterEvent, NoteEvent, Wrap},
+    midi_event::{MIDIEvent, MIDIRouterEvent, Note, NoteEvent},
     scheduler::SchedulerHandler,
+    tempo::StepTimer,
 };
 use schemars::JsonSchema;
 use serde::Deserialize;
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+pub enum ArpeggioMode {
+    /// In the order the keys were pressed
+    #[default]
+    AsPlayed,
+    /// Lowest to highest
+    Up,
+    /// Highest to lowest
+    Down,
+    /// Up then down, playing the lowest and highest notes twice
+    UpDownInclusive,
+    /// From the outside in: lowest, highest, second lowest, second highest...
+    Converge,
+    /// From the inside out, the opposite of `Converge`
+    Diverge,
+    /// A random note on every step
+    Random,
+    /// Moves one note up or down at random on every step
+    RandomWalk,
+    /// Every note at once on every step
+    Chord,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub enum LatchToggle {
+    /// Every press of this note, e.g. `C1`, turns latching on or off
+    Note(Note),
+    /// Latches while this controller is at 64 or above
+    Controller(u8),
+    /// Latches while the sustain pedal (CC 64) is down
+    Sustain,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub enum ArpeggioVelocity {
+    /// The velocity each key was pressed with
+    #[default]
+    Original,
+    Fixed(u8),
+    /// Velocities cycled through on every step, e.g. `[127, 60, 90, 60]`
+    Accents(Vec<u8>),
+}
+
+const MODES: [ArpeggioMode; 9] = [
+    ArpeggioMode::AsPlayed,
+    ArpeggioMode::Up,
+    ArpeggioMode::Down,
+    ArpeggioMode::UpDownInclusive,
+    ArpeggioMode::Converge,
+    ArpeggioMode::Diverge,
+    ArpeggioMode::Random,
+    ArpeggioMode::RandomWalk,
+    ArpeggioMode::Chord,
+];
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct ArpeggioTransformOptions {
     subdivision: f64,
-    direction: CycleDirection,
+    /// How the notes lined up by `mode` are walked through. Defaults to `Forward`
+    direction: Option<CycleDirection>,
     repeat: Option<u64>,
     note_duration: Option<u64>,
+    /// How the held notes are lined up. Defaults to `AsPlayed`
+    mode: Option<ArpeggioMode>,
+    /// Spreads the held notes over this many octaves. Defaults to 1
+    octaves: Option<u8>,
+    /// Picks the mode live from this controller, its values spread evenly over `modes`
+    mode_controller: Option<u8>,
+    /// Modes to pick from with `mode_controller`. Defaults to every mode
+    modes: Option<Vec<ArpeggioMode>>,
+    /// Keeps playing the last chord once its keys are released, until a new chord is struck.
+    /// Defaults to false
+    latch: Option<bool>,
+    /// Turns latching on and off while playing
+    latch_toggle: Option<LatchToggle>,
+    /// How long each note lasts, as a percentage of the step length. Takes precedence over
+    /// `note_duration`, which is still used until the step length is known
+    gate: Option<f64>,
+    /// Holds each note until the next step, tying notes that are played on consecutive steps.
+    /// Takes precedence over `gate` and `note_duration`
+    legato: Option<bool>,
+    /// Defaults to `Original`
+    velocity: Option<ArpeggioVelocity>,
+    /// Device the notes are played on. Defaults to the device of the key that played them
+    output_device: Option<String>,
+    /// Channel the notes are played on. Defaults to the channel of the key that played them
+    channel: Option<u8>,
+}
+
+// A held key, along with the device it came from
+#[derive(Debug, Clone)]
+struct Key {
+    device: String,
+    note: NoteEvent,
 }
 
 #[derive(Debug)]
 pub struct ArpeggioTransform {
     tempo_subdiv: Option<f64>,
-    pressed_keys: Vec<NoteEvent>,
+    // Notes being arpeggiated, which might no longer be held when latching
+    pressed_keys: Vec<Key>,
+    held_keys: Vec<Key>,
     note_duration: u64,
-    cycle_iter: Cycle<NoteEvent>,
+    direction: CycleDirection,
+    mode: ArpeggioMode,
+    octaves: u8,
+    mode_controller: Option<u8>,
+    modes: Vec<ArpeggioMode>,
+    latch: bool,
+    latch_note: Option<u8>,
+    latch_controller: Option<u8>,
+    gate: Option<f64>,
+    legato: bool,
+    velocity: ArpeggioVelocity,
+    step: usize,
+    step_timer: StepTimer,
+    // Notes held until the next step when playing legato
+    sounding: Vec<Key>,
+    output_device: Option<String>,
+    channel: Option<u8>,
+    cycle_iter: Cycle<Key>,
+}
+
+impl Key {
+    fn note_on(&self) -> MIDIRouterEvent {
+        MIDIRouterEvent {
+            device: self.device.clone(),
+            event: MIDIEvent::NoteOn(self.note.clone()),
+        }
+    }
+
+    fn note_off(&self) -> MIDIRouterEvent {
+        MIDIRouterEvent {
+            device: self.device.clone(),
+            event: MIDIEvent::NoteOff(self.note.get_note_off()),
+        }
+    }
 }
 
 impl ArpeggioTransform {
     pub fn from_config(config: ArpeggioTransformOptions) -> ArpeggioTransform {
+        let direction = config.direction.unwrap_or(CycleDirection::Forward);
+        let mode = config.mode.unwrap_or_default();
+        let (latch_note, latch_controller) = match config.latch_toggle {
+            Some(LatchToggle::Note(note)) => (Some(note.into()), None),
+            Some(LatchToggle::Controller(controller)) => (None, Some(controller)),
+            Some(LatchToggle::Sustain) => (None, Some(64)),
+            None => (None, None),
+        };
+
         ArpeggioTransform {
             tempo_subdiv: Some(config.subdivision),
             pressed_keys: vec![],
+            held_keys: vec![],
             note_duration: config.note_duration.unwrap_or(250),
-            cycle_iter: Cycle::new(vec![], config.direction.clone(), config.repeat),
+            direction: direction.clone(),
+            mode,
+            octaves: config.octaves.unwrap_or(1).max(1),
+            mode_controller: config.mode_controller,
+            modes: config
+                .modes
+                .filter(|modes| !modes.is_empty())
+                .unwrap_or(MODES.to_vec()),
+            latch: config.latch.unwrap_or(false),
+            latch_note,
+            latch_controller,
+            gate: config.gate,
+            legato: config.legato.unwrap_or(false),
+            velocity: config.velocity.unwrap_or_default(),
+            step: 0,
+            step_timer: StepTimer::default(),
+            sounding: vec![],
+            output_device: config.output_device,
+            channel: config.channel,
+            cycle_iter: Cycle::new(
+                vec![],
+                Self::cycle_direction(mode, direction),
+                config.repeat,
+            ),
         }
     }
+
+    fn cycle_direction(mode: ArpeggioMode, direction: CycleDirection) -> CycleDirection {
+        match mode {
+            ArpeggioMode::Random => CycleDirection::Random,
+            ArpeggioMode::RandomWalk => CycleDirection::RandomWalk,
+            _ => direction,
+        }
+    }
+
+    /// Lines up the held notes, spread over the octaves, in the order they should be played
+    fn arrange(mode: ArpeggioMode, keys: &[Key], octaves: u8) -> Vec<Key> {
+        let mut notes: Vec<Key> = (0..octaves as u16)
+            .flat_map(|octave| {
+                keys.iter().filter_map(move |key| {
+                    let note = key.note.note as u16 + octave * 12;
+
+                    (note <= 127).then(|| Key {
+                        device: key.device.clone(),
+                        note: NoteEvent {
+                            note: note as u8,
+                            ..key.note.clone()
+                        },
+                    })
+                })
+            })
+            .collect();
+
+        if mode != ArpeggioMode::AsPlayed {
+            notes.sort_by_key(|key| key.note.note);
+        }
+
+        match mode {
+            ArpeggioMode::Down => notes.reverse(),
+            ArpeggioMode::UpDownInclusive => {
+                let down: Vec<Key> = notes.iter().rev().cloned().collect();
+                notes.extend(down);
+            }
+            ArpeggioMode::Converge | ArpeggioMode::Diverge => {
+                let mut outside_in = vec![];
+                let (mut low, mut high) = (0, notes.len());
+
+                while low < high {
+                    outside_in.push(notes[low].clone());
+                    low += 1;
+
+                    if low < high {
+                        high -= 1;
+                        outside_in.push(notes[high].clone());
+                    }
+                }
+
+                if mode == ArpeggioMode::Diverge {
+                    outside_in.reverse();
+                }
+
+                notes = outside_in;
+            }
+            _ => {}
+        }
+
+        notes
+    }
+
+    fn update_notes(&mut self) {
+        self.cycle_iter
+            .update_vec(Self::arrange(self.mode, &self.pressed_keys, self.octaves));
+    }
+
+    fn note_duration(&self) -> u64 {
+        match (self.gate, self.step_timer.step()) {
+            (Some(gate), Some(step)) => (step.as_secs_f64() * 1000.0 * gate / 100.0) as u64,
+            _ => self.note_duration,
+        }
+    }
+
+    fn step_velocity(&self, velocity: u8) -> u8 {
+        match &self.velocity {
+            ArpeggioVelocity::Original => velocity,
+            ArpeggioVelocity::Fixed(velocity) => *velocity,
+            ArpeggioVelocity::Accents(accents) if accents.is_empty() => velocity,
+            ArpeggioVelocity::Accents(accents) => accents[self.step % accents.len()],
+        }
+        .clamp(1, 127)
+    }
+
+    fn set_latch(&mut self, latch: bool) {
+        self.latch = latch;
+
+        // Let go of the notes that are no longer held
+        if !latch {
+            self.pressed_keys = self.held_keys.clone();
+            self.update_notes();
+        }
+    }
+
+    fn set_mode(&mut self, mode: ArpeggioMode) {
+        self.mode = mode;
+        self.cycle_iter
+            .set_direction(Self::cycle_direction(mode, self.direction.clone()));
+        self.update_notes();
+    }
 }
 
 impl Transform for ArpeggioTransform {
@@ -40,15 +298,60 @@ impl Transform for ArpeggioTransform {
     }
 
     fn on_tick(&mut self, scheduler: &SchedulerHandler) -> Option<MIDIRouterEvent> {
+        self.step_timer.tick();
+
         if self.pressed_keys.is_empty() {
+            // Let go of whatever was held for legato
+            self.sounding
+                .drain(..)
+                .for_each(|key| scheduler.send_now(key.note_off()));
+
             return None;
         }
 
-        let note_on = self.cycle_iter.next().clone();
-        let note_off = note_on.get_note_off();
+        let notes: Vec<Key> = match self.mode {
+            ArpeggioMode::Chord => Self::arrange(self.mode, &self.pressed_keys, self.octaves),
+            _ => vec![self.cycle_iter.next().clone()],
+        }
+        .into_iter()
+        .map(|key| Key {
+            note: NoteEvent {
+                velocity: self.step_velocity(key.note.velocity),
+                ..key.note
+            },
+            ..key
+        })
+        .collect();
+        self.step += 1;
+
+        if self.legato {
+            let is_tied = |a: &Key, b: &Key| {
+                a.device == b.device
+                    && a.note.note == b.note.note
+                    && a.note.channel == b.note.channel
+            };
+
+            // Notes played again on this step are tied rather than played again
+            self.sounding
+                .iter()
+                .filter(|sounding| !notes.iter().any(|note| is_tied(note, sounding)))
+                .for_each(|sounding| scheduler.send_now(sounding.note_off()));
+            notes
+                .iter()
+                .filter(|note| !self.sounding.iter().any(|sounding| is_tied(note, sounding)))
+                .for_each(|note| scheduler.send_now(note.note_on()));
+
+            self.sounding = notes;
+
+            return None;
+        }
+
+        let note_duration = self.note_duration();
+        notes.into_iter().for_each(|key| {
+            scheduler.send_later(key.note_off(), note_duration);
+            scheduler.send_now(key.note_on());
+        });
 
-        scheduler.send_later(note_off.wrap(), self.note_duration);
-        scheduler.send_now(note_on.wrap());
         None
     }
 
@@ -58,25 +361,244 @@ impl Transform for ArpeggioTransform {
         _scheduler: &SchedulerHandler,
     ) -> Option<MIDIRouterEvent> {
         match message.event {
+            MIDIEvent::NoteOn(ref note) | MIDIEvent::NoteOff(ref note)
+                if Some(note.note) == self.latch_note =>
+            {
+                if matches!(message.event, MIDIEvent::NoteOn(_)) {
+                    self.set_latch(!self.latch);
+                }
+
+                None
+            }
+
             MIDIEvent::NoteOff(NoteEvent { note, .. }) => {
                 // Remove the current key by its note from the set of keys
-                self.pressed_keys.retain(
-                    |NoteEvent {
-                         note: stored_note, ..
-                     }| *stored_note != note,
-                );
-                self.cycle_iter.update_vec(self.pressed_keys.clone());
+                let is_other_note = |key: &Key| key.note.note != note;
+
+                self.held_keys.retain(is_other_note);
+
+                // Latched notes keep playing until a new chord is struck
+                if !self.latch {
+                    self.pressed_keys.retain(is_other_note);
+                    self.update_notes();
+                }
 
                 None
             }
 
             MIDIEvent::NoteOn(note) => {
-                self.pressed_keys.push(note);
-                self.cycle_iter.update_vec(self.pressed_keys.clone());
+                // First key of a new chord
+                if self.latch && self.held_keys.is_empty() {
+                    self.pressed_keys.clear();
+                }
+
+                let key = Key {
+                    device: self
+                        .output_device
+                        .clone()
+                        .unwrap_or_else(|| message.device.clone()),
+                    note: NoteEvent {
+                        channel: self.channel.unwrap_or(note.channel),
+                        ..note
+                    },
+                };
+
+                self.held_keys.push(key.clone());
+                self.pressed_keys.push(key);
+                self.update_notes();
 
                 None
             }
+
+            MIDIEvent::Controller(ref controller)
+                if Some(controller.controller) == self.latch_controller =>
+            {
+                let latch = controller.value >= 64;
+
+                if latch != self.latch {
+                    self.set_latch(latch);
+                }
+
+                None
+            }
+
+            MIDIEvent::Controller(ref controller)
+                if Some(controller.controller) == self.mode_controller =>
+            {
+                let index = controller.value as usize * self.modes.len() / 128;
+                let mode = self.modes[index];
+
+                if mode != self.mode {
+                    self.set_mode(mode);
+                }
+
+                None
+            }
+
             _ => Some(message),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ArpeggioMode, ArpeggioTransform, ArpeggioTransformOptions, ArpeggioVelocity, Key,
+        LatchToggle,
+    };
+    use crate::{
+        app::MIDIMapperEvent,
+        midi_event::{note_event as note, Controller, MIDIEvent, MIDIRouterEvent, NoteEvent},
+        scheduler::Scheduler,
+        transforms::Transform,
+    };
+
+    fn arrange(mode: ArpeggioMode, keys: &[u8], octaves: u8) -> Vec<u8> {
+        let keys: Vec<Key> = keys
+            .iter()
+            .map(|note| Key {
+                device: "keys".into(),
+                note: NoteEvent {
+                    channel: 0,
+                    note: *note,
+                    velocity: 100,
+                },
+            })
+            .collect();
+
+        ArpeggioTransform::arrange(mode, &keys, octaves)
+            .into_iter()
+            .map(|key| key.note.note)
+            .collect()
+    }
+
+    #[test]
+    fn arranges_notes() {
+        let keys = [64, 60, 67, 72];
+
+        assert_eq!(arrange(ArpeggioMode::AsPlayed, &keys, 1), [64, 60, 67, 72]);
+        assert_eq!(arrange(ArpeggioMode::Up, &keys, 1), [60, 64, 67, 72]);
+        assert_eq!(arrange(ArpeggioMode::Down, &keys, 1), [72, 67, 64, 60]);
+        assert_eq!(
+            arrange(ArpeggioMode::UpDownInclusive, &keys[..3], 1),
+            [60, 64, 67, 67, 64, 60]
+        );
+        assert_eq!(arrange(ArpeggioMode::Converge, &keys, 1), [60, 72, 64, 67]);
+        assert_eq!(arrange(ArpeggioMode::Diverge, &keys, 1), [67, 64, 72, 60]);
+    }
+
+    #[test]
+    fn spreads_notes_over_octaves() {
+        assert_eq!(
+            arrange(ArpeggioMode::AsPlayed, &[64, 60], 2),
+            [64, 60, 76, 72]
+        );
+        assert_eq!(arrange(ArpeggioMode::Up, &[64, 60], 2), [60, 64, 72, 76]);
+        assert_eq!(arrange(ArpeggioMode::Up, &[120], 3), [120]);
+    }
+
+    fn playing(transform: &mut ArpeggioTransform, events: Vec<MIDIEvent>) -> Vec<u8> {
+        let (_scheduler, scheduler_handler) = Scheduler::new();
+
+        events.into_iter().for_each(|event| {
+            transform.process_message(
+                MIDIMapperEvent::RouterMessage(MIDIRouterEvent {
+                    device: "keys".into(),
+                    event,
+                }),
+                &scheduler_handler,
+            );
+        });
+
+        transform
+            .pressed_keys
+            .iter()
+            .map(|key| key.note.note)
+            .collect()
+    }
+
+    #[test]
+    fn latches_until_a_new_chord_is_struck() {
+        let mut transform = ArpeggioTransform::from_config(ArpeggioTransformOptions {
+            subdivision: 0.25,
+            direction: None,
+            repeat: None,
+            note_duration: None,
+            mode: None,
+            octaves: None,
+            mode_controller: None,
+            modes: None,
+            latch: None,
+            latch_toggle: Some(LatchToggle::Sustain),
+            gate: None,
+            legato: None,
+            velocity: None,
+            output_device: None,
+            channel: None,
+        });
+        let sustain = |value| {
+            MIDIEvent::Controller(Controller {
+                channel: 0,
+                controller: 64,
+                value,
+            })
+        };
+
+        assert_eq!(
+            playing(
+                &mut transform,
+                vec![sustain(127), note(60, 100), note(64, 100), note(60, 0)]
+            ),
+            [60, 64]
+        );
+        assert_eq!(playing(&mut transform, vec![note(64, 0)]), [60, 64]);
+        assert_eq!(playing(&mut transform, vec![note(62, 100)]), [62]);
+
+        // Releasing the pedal lets go of the notes no longer held
+        assert_eq!(
+            playing(&mut transform, vec![note(65, 100), note(62, 0), sustain(0)]),
+            [65]
+        );
+        assert_eq!(playing(&mut transform, vec![note(65, 0)]), [] as [u8; 0]);
+    }
+
+    #[test]
+    fn plays_legato_with_accents() {
+        let mut transform = ArpeggioTransform::from_config(ArpeggioTransformOptions {
+            subdivision: 0.25,
+            direction: None,
+            repeat: None,
+            note_duration: None,
+            mode: Some(ArpeggioMode::Up),
+            octaves: None,
+            mode_controller: None,
+            modes: None,
+            latch: None,
+            latch_toggle: None,
+            gate: None,
+            legato: Some(true),
+            velocity: Some(ArpeggioVelocity::Accents(vec![127, 50])),
+            output_device: None,
+            channel: None,
+        });
+        let (scheduler, scheduler_handler) = Scheduler::new();
+        let mut tick = |events: Vec<MIDIEvent>| {
+            playing(&mut transform, events);
+            transform.process_message(MIDIMapperEvent::Tick, &scheduler_handler);
+
+            scheduler
+                .receiver
+                .drain()
+                .map(|message| message.event)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(tick(vec![note(64, 100), note(60, 100)]), [note(60, 127)]);
+        assert_eq!(tick(vec![]), [note(60, 0), note(64, 50)]);
+
+        // A note played on consecutive steps is tied
+        assert_eq!(tick(vec![note(60, 0)]), []);
+        assert_eq!(tick(vec![]), []);
+        assert_eq!(tick(vec![note(64, 0)]), [note(64, 0)]);
+    }
+}
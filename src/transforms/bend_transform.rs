@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::{de, Deserialize, Deserializer};
+
+use super::Transform;
+use crate::{
+    midi_event::{Controller, MIDIEvent, MIDIRouterEvent, PitchBend},
+    scheduler::SchedulerHandler,
+    tempo::PPQN,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BendTransformOptions {
+    /// Bend range of the incoming device, in semitones. Defaults to 2
+    #[serde(default, deserialize_with = "deserialize_range")]
+    #[schemars(range(min = 0.0))]
+    pub input_range: Option<f64>,
+    /// Bend range of the receiving device, in semitones. Defaults to `input_range`
+    #[serde(default, deserialize_with = "deserialize_range")]
+    #[schemars(range(min = 0.0))]
+    pub output_range: Option<f64>,
+    /// Glides towards every new bend instead of jumping to it. This is the fraction of the
+    /// remaining distance kept on every clock pulse, between 0.0 (no smoothing) and 1.0 (never
+    /// gets there)
+    pub smoothing: Option<f64>,
+    /// Sends the bend as this controller instead, with the center position at 64
+    pub controller: Option<u8>,
+}
+
+fn deserialize_range<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    let range = Option::<f64>::deserialize(deserializer)?;
+
+    match range {
+        Some(semitones) if semitones <= 0.0 || !semitones.is_finite() => Err(de::Error::custom(
+            format!("Bend range must be above 0 semitones, got {semitones}"),
+        )),
+        _ => Ok(range),
+    }
+}
+
+struct Glide {
+    current: f64,
+    target: f64,
+}
+
+pub struct BendTransform {
+    scale: f64,
+    smoothing: f64,
+    controller: Option<u8>,
+    // Bend sent and bend being glided towards, by device and channel
+    glides: HashMap<(String, u8), Glide>,
+}
+
+impl BendTransform {
+    pub fn from_config(options: BendTransformOptions) -> Self {
+        let input_range = options.input_range.unwrap_or(2.0);
+        let output_range = options.output_range.unwrap_or(input_range);
+
+        Self {
+            scale: input_range / output_range,
+            smoothing: options.smoothing.unwrap_or(0.0).clamp(0.0, 0.99),
+            controller: options.controller,
+            glides: HashMap::new(),
+        }
+    }
+
+    fn to_event(&self, channel: u8, bend: f64) -> MIDIEvent {
+        match self.controller {
+            Some(controller) => MIDIEvent::Controller(Controller {
+                channel,
+                controller,
+                value: ((bend + 1.0) / 2.0 * 127.0).round().clamp(0.0, 127.0) as u8,
+            }),
+            None => MIDIEvent::PitchBend(PitchBend::from_normalized(channel, bend)),
+        }
+    }
+}
+
+impl Transform for BendTransform {
+    fn get_tempo_subdiv(&self) -> Option<f64> {
+        // Every clock pulse
+        (self.smoothing > 0.0).then_some(1.0 / PPQN)
+    }
+
+    fn on_tick(&mut self, scheduler: &SchedulerHandler) -> Option<MIDIRouterEvent> {
+        let smoothing = self.smoothing;
+        let mut bends = vec![];
+
+        self.glides
+            .iter_mut()
+            .filter(|(_, glide)| glide.current != glide.target)
+            .for_each(|((device, channel), glide)| {
+                glide.current = glide.target + (glide.current - glide.target) * smoothing;
+
+                // Close enough to land on the target, so the wheel can rest back at the center
+                if (glide.current - glide.target).abs() < 1.0 / 8192.0 {
+                    glide.current = glide.target;
+                }
+
+                bends.push((device.clone(), *channel, glide.current));
+            });
+
+        bends.into_iter().for_each(|(device, channel, bend)| {
+            scheduler.send_now(MIDIRouterEvent {
+                device,
+                event: self.to_event(channel, bend),
+            })
+        });
+
+        None
+    }
+
+    fn on_message(
+        &mut self,
+        mut message: MIDIRouterEvent,
+        _scheduler: &SchedulerHandler,
+    ) -> Option<MIDIRouterEvent> {
+        let MIDIEvent::PitchBend(bend) = &message.event else {
+            return Some(message);
+        };
+
+        let channel = bend.channel;
+        let target = (bend.normalized() * self.scale).clamp(-1.0, 1.0);
+
+        if self.smoothing > 0.0 {
+            self.glides
+                .entry((message.device.clone(), channel))
+                .or_insert(Glide {
+                    current: 0.0,
+                    target: 0.0,
+                })
+                .target = target;
+
+            return None;
+        }
+
+        match &mut message.event {
+            MIDIEvent::PitchBend(bend) if self.controller.is_none() => bend.set_normalized(target),
+            _ => message.event = self.to_event(channel, target),
+        }
+
+        Some(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BendTransform, BendTransformOptions};
+    use crate::{
+        app::MIDIMapperEvent,
+        midi_event::{Controller, MIDIEvent, MIDIRouterEvent, PitchBend},
+        scheduler::Scheduler,
+        transforms::Transform,
+    };
+
+    fn options() -> BendTransformOptions {
+        BendTransformOptions {
+            input_range: None,
+            output_range: None,
+            smoothing: None,
+            controller: None,
+        }
+    }
+
+    fn bend(value: i16) -> MIDIMapperEvent {
+        MIDIMapperEvent::RouterMessage(MIDIRouterEvent {
+            device: "wheel".into(),
+            event: MIDIEvent::PitchBend(PitchBend::new(0, value)),
+        })
+    }
+
+    fn normalized(value: f64) -> MIDIEvent {
+        MIDIEvent::PitchBend(PitchBend::from_normalized(0, value))
+    }
+
+    #[test]
+    fn scales_between_bend_ranges() {
+        let mut transform = BendTransform::from_config(BendTransformOptions {
+            input_range: Some(2.0),
+            output_range: Some(12.0),
+            ..options()
+        });
+        let (_scheduler, scheduler_handler) = Scheduler::new();
+        let mut play = |value| {
+            transform
+                .process_message(bend(value), &scheduler_handler)
+                .map(|message| message.event)
+        };
+
+        assert_eq!(play(8191), Some(normalized(2.0 / 12.0)));
+        assert_eq!(play(-8192), Some(normalized(-2.0 / 12.0)));
+        assert_eq!(play(0), Some(normalized(0.0)));
+    }
+
+    #[test]
+    fn glides_towards_new_bends() {
+        let mut transform = BendTransform::from_config(BendTransformOptions {
+            smoothing: Some(0.5),
+            ..options()
+        });
+        let (scheduler, scheduler_handler) = Scheduler::new();
+        let mut play = |message| {
+            let mut events: Vec<_> = transform
+                .process_message(message, &scheduler_handler)
+                .into_iter()
+                .collect();
+            events.extend(scheduler.receiver.drain());
+
+            events
+                .into_iter()
+                .map(|message| message.event)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(play(MIDIMapperEvent::Tick), []);
+        assert_eq!(play(bend(8191)), []);
+
+        // Half the remaining distance on every clock pulse
+        assert_eq!(play(MIDIMapperEvent::Tick), [normalized(0.5)]);
+        assert_eq!(play(MIDIMapperEvent::Tick), [normalized(0.75)]);
+        assert_eq!(play(MIDIMapperEvent::Tick), [normalized(0.875)]);
+    }
+
+    #[test]
+    fn sends_bends_as_a_controller() {
+        let mut transform = BendTransform::from_config(BendTransformOptions {
+            controller: Some(1),
+            ..options()
+        });
+        let (_scheduler, scheduler_handler) = Scheduler::new();
+        let mut play = |value| {
+            transform
+                .process_message(bend(value), &scheduler_handler)
+                .map(|message| message.event)
+        };
+        let cc = |value| {
+            Some(MIDIEvent::Controller(Controller {
+                channel: 0,
+                controller: 1,
+                value,
+            }))
+        };
+
+        assert_eq!(play(-8192), cc(0));
+        assert_eq!(play(0), cc(64));
+        assert_eq!(play(8191), cc(127));
+    }
+
+    #[test]
+    fn rejects_empty_bend_ranges() {
+        let error = serde_yaml::from_str::<BendTransformOptions>("output_range: 0")
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("Bend range must be above 0 semitones"));
+        assert!(serde_yaml::from_str::<BendTransformOptions>("input_range: 48").is_ok());
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::Transform;
+use crate::{
+    midi_event::{MIDIEvent, MIDIRouterEvent, NoteEvent},
+    scale::{Rounding, Scale, ScaleOptions},
+    scheduler::SchedulerHandler,
+};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub enum ChordQuality {
+    #[serde(alias = "maj")]
+    Major,
+    #[serde(alias = "min")]
+    Minor,
+    #[serde(alias = "dim")]
+    Diminished,
+    #[serde(alias = "aug")]
+    Augmented,
+    #[serde(alias = "sus2")]
+    Sus2,
+    #[serde(alias = "sus4")]
+    Sus4,
+    #[serde(alias = "maj7")]
+    Major7,
+    #[serde(alias = "min7")]
+    Minor7,
+    #[serde(alias = "7")]
+    Dominant7,
+    #[serde(alias = "dim7")]
+    Diminished7,
+    #[serde(alias = "m7b5")]
+    HalfDiminished7,
+    #[serde(alias = "add9")]
+    Add9,
+    #[serde(alias = "5")]
+    Power,
+}
+
+impl ChordQuality {
+    /// Semitones from the root for every note of the chord
+    pub fn intervals(&self) -> Vec<i16> {
+        match self {
+            Self::Major => vec![0, 4, 7],
+            Self::Minor => vec![0, 3, 7],
+            Self::Diminished => vec![0, 3, 6],
+            Self::Augmented => vec![0, 4, 8],
+            Self::Sus2 => vec![0, 2, 7],
+            Self::Sus4 => vec![0, 5, 7],
+            Self::Major7 => vec![0, 4, 7, 11],
+            Self::Minor7 => vec![0, 3, 7, 10],
+            Self::Dominant7 => vec![0, 4, 7, 10],
+            Self::Diminished7 => vec![0, 3, 6, 9],
+            Self::HalfDiminished7 => vec![0, 3, 6, 10],
+            Self::Add9 => vec![0, 4, 7, 14],
+            Self::Power => vec![0, 7, 12],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub enum Voicing {
+    /// Every note as close as possible to the root
+    #[default]
+    Close,
+    /// Every other note moved up an octave
+    Open,
+    /// The second highest note moved down an octave
+    Drop2,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ChordTransformOptions {
+    /// Defaults to `Major`
+    pub quality: Option<ChordQuality>,
+    /// Semitones from the played note, e.g. `[0, 4, 7, 11]`. Takes precedence over `quality`
+    pub intervals: Option<Vec<i16>>,
+    /// Builds the chord by stacking thirds within this scale, instead of using `quality` or `intervals`
+    pub scale: Option<ScaleOptions>,
+    /// How many notes to stack when using a `scale`. Defaults to 3
+    pub voices: Option<u8>,
+    /// How many times the lowest note is moved up an octave
+    pub inversion: Option<u8>,
+    pub voicing: Option<Voicing>,
+    /// Velocity multiplier for each note of the chord, starting from the root, e.g. `[1.0, 0.8, 0.8]`
+    pub velocities: Option<Vec<f64>>,
+}
+
+pub struct ChordTransform {
+    intervals: Vec<i16>,
+    scale: Option<Scale>,
+    voices: u8,
+    inversion: u8,
+    voicing: Voicing,
+    velocities: Vec<f64>,
+    // Notes of the chord played for every incoming note on
+    sounding: HashMap<(String, u8, u8), Vec<u8>>,
+    // How many chords are holding each note, as overlapping chords can share some
+    held: HashMap<(String, u8, u8), usize>,
+}
+
+impl ChordTransform {
+    pub fn from_config(options: ChordTransformOptions) -> Self {
+        Self {
+            intervals: options
+                .intervals
+                .unwrap_or_else(|| options.quality.unwrap_or(ChordQuality::Major).intervals()),
+            scale: options.scale.map(Scale::from_config),
+            voices: options.voices.unwrap_or(3),
+            inversion: options.inversion.unwrap_or(0),
+            voicing: options.voicing.unwrap_or_default(),
+            velocities: options.velocities.unwrap_or_default(),
+            sounding: HashMap::new(),
+            held: HashMap::new(),
+        }
+    }
+
+    /// Returns the (note, velocity) pairs making up the chord for the given note
+    fn build_chord(&self, note: &NoteEvent) -> Vec<(u8, u8)> {
+        let chord: Vec<i16> = match &self.scale {
+            Some(scale) => match scale.snap(note.note, Rounding::Nearest) {
+                Some(root) => (0..self.voices as i16)
+                    .map_while(|voice| scale.step(root, voice * 2))
+                    .map(i16::from)
+                    .collect(),
+                None => vec![note.note as i16],
+            },
+            None => self
+                .intervals
+                .iter()
+                .map(|interval| note.note as i16 + interval)
+                .collect(),
+        };
+
+        // Voices are indexed by their position in the chord before inverting and voicing it
+        let mut voices: Vec<(usize, i16)> = chord.into_iter().enumerate().collect();
+        voices.sort_by_key(|(_, note)| *note);
+
+        if !voices.is_empty() {
+            (0..self.inversion).for_each(|_| {
+                voices[0].1 += 12;
+                voices.sort_by_key(|(_, note)| *note);
+            });
+        }
+
+        match self.voicing {
+            Voicing::Close => {}
+            Voicing::Open => voices
+                .iter_mut()
+                .skip(1)
+                .step_by(2)
+                .for_each(|(_, note)| *note += 12),
+            Voicing::Drop2 if voices.len() > 1 => {
+                let second_highest = voices.len() - 2;
+                voices[second_highest].1 -= 12;
+            }
+            Voicing::Drop2 => {}
+        }
+
+        let mut result: Vec<(u8, u8)> = vec![];
+        voices
+            .into_iter()
+            .filter(|(_, note)| (0..=127).contains(note))
+            .for_each(|(voice, chord_note)| {
+                let scale = self.velocities.get(voice).unwrap_or(&1.0);
+                let velocity = (note.velocity as f64 * scale).round().clamp(1.0, 127.0) as u8;
+
+                if !result.iter().any(|(n, _)| *n == chord_note as u8) {
+                    result.push((chord_note as u8, velocity));
+                }
+            });
+
+        result
+    }
+}
+
+impl Transform for ChordTransform {
+    fn on_message(
+        &mut self,
+        message: MIDIRouterEvent,
+        scheduler: &SchedulerHandler,
+    ) -> Option<MIDIRouterEvent> {
+        match &message.event {
+            MIDIEvent::NoteOn(note) => {
+                let chord = self.build_chord(note);
+
+                chord.iter().for_each(|(chord_note, velocity)| {
+                    *self
+                        .held
+                        .entry((message.device.clone(), note.channel, *chord_note))
+                        .or_default() += 1;
+
+                    scheduler.send_now(MIDIRouterEvent {
+                        device: message.device.clone(),
+                        event: MIDIEvent::NoteOn(NoteEvent {
+                            channel: note.channel,
+                            note: *chord_note,
+                            velocity: *velocity,
+                        }),
+                    })
+                });
+
+                self.sounding.insert(
+                    (message.device.clone(), note.channel, note.note),
+                    chord
+                        .into_iter()
+                        .map(|(chord_note, _)| chord_note)
+                        .collect(),
+                );
+
+                None
+            }
+
+            MIDIEvent::NoteOff(note) => {
+                let chord =
+                    self.sounding
+                        .remove(&(message.device.clone(), note.channel, note.note))?;
+
+                // Only release the notes no other chord is holding
+                chord.into_iter().for_each(|chord_note| {
+                    let key = (message.device.clone(), note.channel, chord_note);
+                    match self.held.get_mut(&key) {
+                        Some(count) if *count > 1 => {
+                            *count -= 1;
+                            return;
+                        }
+                        _ => {
+                            self.held.remove(&key);
+                        }
+                    }
+
+                    scheduler.send_now(MIDIRouterEvent {
+                        device: message.device.clone(),
+                        event: MIDIEvent::NoteOff(NoteEvent {
+                            note: chord_note,
+                            ..note.clone()
+                        }),
+                    })
+                });
+
+                None
+            }
+
+            _ => Some(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChordQuality, ChordTransform, ChordTransformOptions};
+    use crate::{
+        app::MIDIMapperEvent,
+        midi_event::{note_event as note, MIDIRouterEvent, NoteEvent},
+        scale::ScaleOptions,
+        scheduler::Scheduler,
+        transforms::Transform,
+    };
+
+    fn options() -> ChordTransformOptions {
+        ChordTransformOptions {
+            quality: None,
+            intervals: None,
+            scale: None,
+            voices: None,
+            inversion: None,
+            voicing: None,
+            velocities: None,
+        }
+    }
+
+    fn chord(options: ChordTransformOptions, root: u8) -> Vec<(u8, u8)> {
+        ChordTransform::from_config(options).build_chord(&NoteEvent {
+            channel: 0,
+            note: root,
+            velocity: 100,
+        })
+    }
+
+    #[test]
+    fn builds_chords() {
+        let minor7 = ChordTransformOptions {
+            quality: Some(ChordQuality::Minor7),
+            velocities: Some(vec![1.0, 0.5]),
+            ..options()
+        };
+        assert_eq!(
+            chord(minor7, 60),
+            [(60, 100), (63, 50), (67, 100), (70, 100)]
+        );
+
+        // Thirds stacked within C major from D
+        let scale = ChordTransformOptions {
+            scale: Some(ScaleOptions {
+                root: "C".parse().unwrap(),
+                mode: None,
+                pitch_classes: None,
+            }),
+            ..options()
+        };
+        assert_eq!(chord(scale, 62), [(62, 100), (65, 100), (69, 100)]);
+    }
+
+    #[test]
+    fn inverts_from_the_lowest_note() {
+        let second = ChordTransformOptions {
+            inversion: Some(2),
+            ..options()
+        };
+        assert_eq!(chord(second, 60), [(67, 100), (72, 100), (76, 100)]);
+
+        // Intervals out of order still move the lowest note, velocities following their voice
+        let unordered = ChordTransformOptions {
+            intervals: Some(vec![7, 0, 4]),
+            inversion: Some(1),
+            velocities: Some(vec![1.0, 0.5, 0.8]),
+            ..options()
+        };
+        assert_eq!(chord(unordered, 60), [(64, 80), (67, 100), (72, 50)]);
+    }
+
+    #[test]
+    fn keeps_shared_notes_until_every_chord_is_released() {
+        let mut transform = ChordTransform::from_config(options());
+        let (scheduler, scheduler_handler) = Scheduler::new();
+        let mut play = |note_number: u8, velocity: u8| {
+            let result = transform.process_message(
+                MIDIMapperEvent::RouterMessage(MIDIRouterEvent {
+                    device: "keys".into(),
+                    event: note(note_number, velocity),
+                }),
+                &scheduler_handler,
+            );
+            assert_eq!(result, None);
+
+            scheduler
+                .receiver
+                .drain()
+                .map(|message| message.event)
+                .collect::<Vec<_>>()
+        };
+
+        // C and E major share E
+        assert_eq!(play(60, 100), [note(60, 100), note(64, 100), note(67, 100)]);
+        assert_eq!(play(64, 100), [note(64, 100), note(68, 100), note(71, 100)]);
+
+        assert_eq!(play(60, 0), [note(60, 0), note(67, 0)]);
+        assert_eq!(play(64, 0), [note(64, 0), note(68, 0), note(71, 0)]);
+    }
+}
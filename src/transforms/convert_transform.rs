@@ -0,0 +1,389 @@
+use std::collections::{HashMap, HashSet};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::Transform;
+use crate::{
+    midi_event::{Controller, MIDIEvent, MIDIRouterEvent, Note, NoteEvent},
+    scheduler::SchedulerHandler,
+};
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub enum NoteToCcMode {
+    /// The controller is on while the note is held
+    #[default]
+    Momentary,
+    /// Every note on flips the controller between on and off
+    Toggle,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct NoteToCcOptions {
+    /// e.g. `C1` or `36`
+    pub note: Note,
+    pub controller: u8,
+    pub mode: Option<NoteToCcMode>,
+    /// Value sent when on. Uses the note velocity if omitted
+    pub value: Option<u8>,
+    /// Value sent when off. Defaults to 0
+    pub off_value: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CcToNoteOptions {
+    pub controller: u8,
+    /// The note plays while the controller is at or above this value. Defaults to 64
+    pub threshold: Option<u8>,
+    pub note: Note,
+    /// Uses the controller value if omitted
+    pub velocity: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ProgramToCcOptions {
+    /// Only converts this program. Converts every program if omitted
+    pub program: Option<u8>,
+    pub controller: u8,
+    /// Uses the program number if omitted
+    pub value: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ConvertTransformOptions {
+    pub notes_to_cc: Option<Vec<NoteToCcOptions>>,
+    pub cc_to_notes: Option<Vec<CcToNoteOptions>>,
+    pub programs_to_cc: Option<Vec<ProgramToCcOptions>>,
+}
+
+struct NoteToCc {
+    controller: u8,
+    mode: NoteToCcMode,
+    value: Option<u8>,
+    off_value: u8,
+}
+
+struct CcToNote {
+    threshold: u8,
+    note: u8,
+    velocity: Option<u8>,
+}
+
+// Converts between notes, controllers and program changes. Anything not converted goes through
+pub struct ConvertTransform {
+    notes_to_cc: HashMap<u8, NoteToCc>,
+    cc_to_notes: HashMap<u8, CcToNote>,
+    programs_to_cc: Vec<ProgramToCcOptions>,
+    // Toggled controllers currently on, by device, channel and controller
+    toggled: HashSet<(String, u8, u8)>,
+    // Controllers currently playing their note
+    playing: HashSet<(String, u8, u8)>,
+}
+
+impl ConvertTransform {
+    pub fn from_config(options: ConvertTransformOptions) -> Self {
+        Self {
+            notes_to_cc: options
+                .notes_to_cc
+                .unwrap_or_default()
+                .into_iter()
+                .map(|options| {
+                    (
+                        options.note.into(),
+                        NoteToCc {
+                            controller: options.controller,
+                            mode: options.mode.unwrap_or_default(),
+                            value: options.value,
+                            off_value: options.off_value.unwrap_or(0),
+                        },
+                    )
+                })
+                .collect(),
+            cc_to_notes: options
+                .cc_to_notes
+                .unwrap_or_default()
+                .into_iter()
+                .map(|options| {
+                    (
+                        options.controller,
+                        CcToNote {
+                            threshold: options.threshold.unwrap_or(64),
+                            note: options.note.into(),
+                            velocity: options.velocity,
+                        },
+                    )
+                })
+                .collect(),
+            programs_to_cc: options.programs_to_cc.unwrap_or_default(),
+            toggled: HashSet::new(),
+            playing: HashSet::new(),
+        }
+    }
+
+    fn note_to_cc(&mut self, device: &str, event: &MIDIEvent) -> Option<Option<MIDIEvent>> {
+        let (note, is_on) = match event {
+            MIDIEvent::NoteOn(note) => (note, true),
+            MIDIEvent::NoteOff(note) => (note, false),
+            _ => return None,
+        };
+        let mapping = self.notes_to_cc.get(&note.note)?;
+
+        let on = match (&mapping.mode, is_on) {
+            (NoteToCcMode::Momentary, is_on) => is_on,
+            (NoteToCcMode::Toggle, true) => {
+                let key = (device.to_string(), note.channel, mapping.controller);
+
+                if !self.toggled.remove(&key) {
+                    self.toggled.insert(key);
+                    true
+                } else {
+                    false
+                }
+            }
+            // Toggles only change on note ons
+            (NoteToCcMode::Toggle, false) => return Some(None),
+        };
+
+        Some(Some(MIDIEvent::Controller(Controller {
+            channel: note.channel,
+            controller: mapping.controller,
+            value: match on {
+                true => mapping.value.unwrap_or(note.velocity),
+                false => mapping.off_value,
+            },
+        })))
+    }
+
+    fn cc_to_note(&mut self, device: &str, event: &MIDIEvent) -> Option<Option<MIDIEvent>> {
+        let MIDIEvent::Controller(controller) = event else {
+            return None;
+        };
+        let mapping = self.cc_to_notes.get(&controller.controller)?;
+
+        let key = (
+            device.to_string(),
+            controller.channel,
+            controller.controller,
+        );
+        let above = controller.value >= mapping.threshold;
+        let note = NoteEvent {
+            channel: controller.channel,
+            note: mapping.note,
+            velocity: mapping.velocity.unwrap_or(controller.value).max(1),
+        };
+
+        // Only crossing the threshold plays or releases the note
+        Some(match (above, self.playing.contains(&key)) {
+            (true, false) => {
+                self.playing.insert(key);
+                Some(MIDIEvent::NoteOn(note))
+            }
+            (false, true) => {
+                self.playing.remove(&key);
+                Some(MIDIEvent::NoteOff(NoteEvent {
+                    velocity: 0,
+                    ..note
+                }))
+            }
+            _ => None,
+        })
+    }
+
+    fn program_to_cc(&self, event: &MIDIEvent) -> Option<Option<MIDIEvent>> {
+        let MIDIEvent::ProgramChange(program) = event else {
+            return None;
+        };
+        let mapping = self
+            .programs_to_cc
+            .iter()
+            .find(|mapping| mapping.program.is_none_or(|p| p == program.program))?;
+
+        Some(Some(MIDIEvent::Controller(Controller {
+            channel: program.channel,
+            controller: mapping.controller,
+            value: mapping.value.unwrap_or(program.program),
+        })))
+    }
+}
+
+impl Transform for ConvertTransform {
+    fn on_message(
+        &mut self,
+        mut message: MIDIRouterEvent,
+        _scheduler: &SchedulerHandler,
+    ) -> Option<MIDIRouterEvent> {
+        // None if nothing converts this event, Some(None) if it's converted into nothing
+        let converted = self
+            .note_to_cc(&message.device, &message.event)
+            .or_else(|| self.cc_to_note(&message.device, &message.event))
+            .or_else(|| self.program_to_cc(&message.event));
+
+        match converted {
+            None => Some(message),
+            Some(event) => {
+                message.event = event?;
+                Some(message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CcToNoteOptions, ConvertTransform, ConvertTransformOptions, NoteToCcMode, NoteToCcOptions,
+        ProgramToCcOptions,
+    };
+    use crate::{
+        midi_event::{
+            note_event as note, Controller, MIDIEvent, MIDIRouterEvent, Note, ProgramChange,
+        },
+        scheduler::Scheduler,
+        transforms::Transform,
+    };
+
+    fn cc(controller: u8, value: u8) -> MIDIEvent {
+        MIDIEvent::Controller(Controller {
+            channel: 0,
+            controller,
+            value,
+        })
+    }
+
+    fn convert(transform: &mut ConvertTransform, events: Vec<MIDIEvent>) -> Vec<Option<MIDIEvent>> {
+        let (_scheduler, scheduler_handler) = Scheduler::new();
+
+        events
+            .into_iter()
+            .map(|event| {
+                transform
+                    .on_message(
+                        MIDIRouterEvent {
+                            device: "pads".into(),
+                            event,
+                        },
+                        &scheduler_handler,
+                    )
+                    .map(|message| message.event)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn converts_notes_to_cc() {
+        let mut transform = ConvertTransform::from_config(ConvertTransformOptions {
+            notes_to_cc: Some(vec![
+                NoteToCcOptions {
+                    note: Note::C1,
+                    controller: 20,
+                    mode: None,
+                    value: None,
+                    off_value: None,
+                },
+                NoteToCcOptions {
+                    note: Note::D1,
+                    controller: 21,
+                    mode: Some(NoteToCcMode::Toggle),
+                    value: Some(127),
+                    off_value: None,
+                },
+            ]),
+            cc_to_notes: None,
+            programs_to_cc: None,
+        });
+
+        assert_eq!(
+            convert(
+                &mut transform,
+                vec![
+                    note(24, 90),
+                    note(24, 0),
+                    note(26, 10),
+                    note(26, 0),
+                    note(26, 10),
+                    note(60, 10),
+                ]
+            ),
+            vec![
+                Some(cc(20, 90)),
+                Some(cc(20, 0)),
+                Some(cc(21, 127)),
+                None,
+                Some(cc(21, 0)),
+                Some(note(60, 10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn converts_cc_thresholds_to_notes() {
+        let mut transform = ConvertTransform::from_config(ConvertTransformOptions {
+            notes_to_cc: None,
+            cc_to_notes: Some(vec![CcToNoteOptions {
+                controller: 64,
+                threshold: None,
+                note: Note::C3,
+                velocity: Some(100),
+            }]),
+            programs_to_cc: None,
+        });
+
+        assert_eq!(
+            convert(
+                &mut transform,
+                vec![cc(64, 10), cc(64, 70), cc(64, 127), cc(64, 20), cc(1, 20)]
+            ),
+            vec![
+                None,
+                Some(note(48, 100)),
+                None,
+                Some(note(48, 0)),
+                Some(cc(1, 20)),
+            ]
+        );
+    }
+
+    fn program(program: u8) -> MIDIEvent {
+        MIDIEvent::ProgramChange(ProgramChange {
+            channel: 0,
+            program,
+        })
+    }
+
+    #[test]
+    fn converts_programs_to_cc() {
+        let mapping = |program, controller, value| ProgramToCcOptions {
+            program,
+            controller,
+            value,
+        };
+        let transform = |programs_to_cc| {
+            ConvertTransform::from_config(ConvertTransformOptions {
+                notes_to_cc: None,
+                cc_to_notes: None,
+                programs_to_cc: Some(programs_to_cc),
+            })
+        };
+
+        let mut specific = transform(vec![
+            mapping(Some(5), 20, None),
+            mapping(Some(6), 21, Some(100)),
+        ]);
+        assert_eq!(
+            convert(&mut specific, vec![program(5), program(6), program(7)]),
+            vec![Some(cc(20, 5)), Some(cc(21, 100)), Some(program(7))]
+        );
+
+        let mut any = transform(vec![mapping(None, 22, None)]);
+        assert_eq!(
+            convert(&mut any, vec![program(3), program(90)]),
+            vec![Some(cc(22, 3)), Some(cc(22, 90))]
+        );
+
+        let mut fixed = transform(vec![mapping(None, 23, Some(127))]);
+        assert_eq!(
+            convert(&mut fixed, vec![program(3), program(90)]),
+            vec![Some(cc(23, 127)), Some(cc(23, 127))]
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use std::{collections::HashMap, time::Instant};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::Transform;
+use crate::{
+    midi_event::{MIDIEvent, MIDIRouterEvent, NoteEvent},
+    scheduler::SchedulerHandler,
+    tempo::StepTimer,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DelayTransformOptions {
+    /// Milliseconds between every repeat. Defaults to 250
+    time: Option<u64>,
+    /// Syncs the time between every repeat to this subdivision of the clock instead, `time` being
+    /// used until the tempo is known
+    subdivision: Option<f64>,
+    /// Defaults to 3
+    repeats: Option<u8>,
+    /// Fraction of the velocity kept on every repeat, between 0.0 and 1.0. Defaults to 0.7
+    decay: Option<f64>,
+    /// Semitones every repeat is transposed by, from the previous one. Defaults to 0
+    transpose: Option<i8>,
+    /// Channels the repeats bounce between. Defaults to the channel of the note
+    channels: Option<Vec<u8>>,
+    /// Devices the repeats bounce between. Defaults to the device of the note
+    devices: Option<Vec<String>>,
+}
+
+// Events, each with how many milliseconds from a given moment they're sent
+type Echoes = Vec<(u64, MIDIRouterEvent)>;
+
+// Device, channel and note of a key
+type Key = (String, u8, u8);
+
+pub struct DelayTransform {
+    time: u64,
+    subdivision: Option<f64>,
+    repeats: u8,
+    decay: f64,
+    transpose: i8,
+    channels: Vec<u8>,
+    devices: Vec<String>,
+    step_timer: StepTimer,
+    // When every note being played started, along with its repeats, by device, channel and note
+    echoes: HashMap<Key, (Instant, Echoes)>,
+}
+
+impl DelayTransform {
+    pub fn from_config(options: DelayTransformOptions) -> Self {
+        Self {
+            time: options.time.unwrap_or(250),
+            subdivision: options.subdivision,
+            repeats: options.repeats.unwrap_or(3),
+            decay: options.decay.unwrap_or(0.7).clamp(0.0, 1.0),
+            transpose: options.transpose.unwrap_or(0),
+            channels: options.channels.unwrap_or_default(),
+            devices: options.devices.unwrap_or_default(),
+            step_timer: StepTimer::default(),
+            echoes: HashMap::new(),
+        }
+    }
+
+    /// Repeats of a note on, in order with their delay, stopping early once they fade out or go
+    /// out of range
+    fn echoes(&self, device: &str, note: &NoteEvent) -> Echoes {
+        let mut velocity = note.velocity as f64;
+        let mut pitch = note.note as i16;
+
+        (0..self.repeats as usize)
+            .map_while(|repeat| {
+                velocity *= self.decay;
+                pitch += self.transpose as i16;
+
+                if velocity.round() < 1.0 || !(0..=127).contains(&pitch) {
+                    return None;
+                }
+
+                let channel = match self.channels.is_empty() {
+                    true => note.channel,
+                    false => self.channels[repeat % self.channels.len()],
+                };
+                let device = match self.devices.is_empty() {
+                    true => device.to_string(),
+                    false => self.devices[repeat % self.devices.len()].clone(),
+                };
+
+                Some((
+                    self.time * (repeat as u64 + 1),
+                    MIDIRouterEvent {
+                        device,
+                        event: MIDIEvent::NoteOn(NoteEvent {
+                            channel,
+                            note: pitch as u8,
+                            velocity: velocity.round() as u8,
+                        }),
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// Whether the repeat plays the very key of the note, which would cut it short while held
+    fn retriggers(key: &Key, echo: &MIDIRouterEvent) -> bool {
+        matches!(&echo.event, MIDIEvent::NoteOn(note)
+            if (&echo.device, note.channel, note.note) == (&key.0, key.1, key.2))
+    }
+
+    fn note_off(echo: &MIDIRouterEvent) -> MIDIRouterEvent {
+        let event = match &echo.event {
+            MIDIEvent::NoteOn(note) => MIDIEvent::NoteOff(note.get_note_off()),
+            event => event.clone(),
+        };
+
+        MIDIRouterEvent {
+            device: echo.device.clone(),
+            event,
+        }
+    }
+
+    /// Note ons and note offs sent as soon as the note is played. Repeats last until just before
+    /// the next one, and are cut short when the note is let go of earlier. Repeats of the very key
+    /// of the note wait for it to be let go of
+    fn play(key: &Key, echoes: &Echoes) -> Echoes {
+        // Repeats are evenly spaced, by the time set when the note was played
+        let spacing = echoes.first().map_or(0, |(delay, _)| *delay);
+        let length = spacing.saturating_sub(1).max(1);
+
+        echoes
+            .iter()
+            .filter(|(_, echo)| !Self::retriggers(key, echo))
+            .flat_map(|(delay, echo)| {
+                [
+                    (*delay, echo.clone()),
+                    (delay + length, Self::note_off(echo)),
+                ]
+            })
+            .collect()
+    }
+
+    /// Events sent once the note is let go of after being held for `held` milliseconds, delayed
+    /// from then. Repeats last as long as the note was held for, so those ending earlier than
+    /// planned get an earlier note off, and repeats of the very key of the note that haven't come
+    /// in yet are played
+    fn release(key: &Key, held: u64, echoes: Echoes) -> Echoes {
+        let spacing = echoes.first().map_or(0, |(delay, _)| *delay);
+        let full_length = spacing.saturating_sub(1).max(1);
+        let length = held.clamp(1, full_length);
+
+        echoes
+            .into_iter()
+            .flat_map(|(delay, echo)| {
+                let note_off = |echo| (delay + length - held, Self::note_off(echo));
+
+                match Self::retriggers(key, &echo) {
+                    // It would have played while the key was still held
+                    true if delay <= held => vec![],
+                    true => vec![(delay - held, echo.clone()), note_off(&echo)],
+                    false if length < full_length => vec![note_off(&echo)],
+                    false => vec![],
+                }
+            })
+            .collect()
+    }
+
+    fn send(events: Echoes, scheduler: &SchedulerHandler) {
+        events
+            .into_iter()
+            .for_each(|(delay, event)| scheduler.send_later(event, delay));
+    }
+}
+
+impl Transform for DelayTransform {
+    fn get_tempo_subdiv(&self) -> Option<f64> {
+        self.subdivision
+    }
+
+    fn on_tick(&mut self, _scheduler: &SchedulerHandler) -> Option<MIDIRouterEvent> {
+        self.step_timer.tick();
+        if let Some(step) = self.step_timer.step() {
+            self.time = step.as_millis() as u64;
+        }
+
+        None
+    }
+
+    fn on_message(
+        &mut self,
+        message: MIDIRouterEvent,
+        scheduler: &SchedulerHandler,
+    ) -> Option<MIDIRouterEvent> {
+        match &message.event {
+            MIDIEvent::NoteOn(note) => {
+                let key = (message.device.clone(), note.channel, note.note);
+
+                // Played again before being let go of: release the previous repeats first
+                if let Some((started, previous)) = self.echoes.remove(&key) {
+                    let held = started.elapsed().as_millis() as u64;
+                    Self::send(Self::release(&key, held, previous), scheduler);
+                }
+
+                let echoes = self.echoes(&message.device, note);
+                Self::send(Self::play(&key, &echoes), scheduler);
+                self.echoes.insert(key, (Instant::now(), echoes));
+            }
+
+            MIDIEvent::NoteOff(note) => {
+                let key = (message.device.clone(), note.channel, note.note);
+
+                if let Some((started, echoes)) = self.echoes.remove(&key) {
+                    let held = started.elapsed().as_millis() as u64;
+                    Self::send(Self::release(&key, held, echoes), scheduler);
+                }
+            }
+
+            _ => {}
+        }
+
+        Some(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DelayTransform, DelayTransformOptions};
+    use crate::midi_event::{note_event, MIDIEvent, NoteEvent};
+
+    #[test]
+    fn repeats_fade_and_bounce() {
+        let transform = DelayTransform::from_config(DelayTransformOptions {
+            time: None,
+            subdivision: None,
+            repeats: Some(5),
+            decay: Some(0.5),
+            transpose: Some(12),
+            channels: Some(vec![1, 2]),
+            devices: None,
+        });
+        let note = NoteEvent {
+            channel: 0,
+            note: 72,
+            velocity: 100,
+        };
+
+        let echoes: Vec<_> = transform
+            .echoes("keys", &note)
+            .into_iter()
+            .map(|(_, echo)| (echo.device, echo.event))
+            .collect();
+
+        let echo = |channel, note, velocity| {
+            (
+                "keys".to_string(),
+                MIDIEvent::NoteOn(NoteEvent {
+                    channel,
+                    note,
+                    velocity,
+                }),
+            )
+        };
+
+        // The 5th repeat would go past the highest note
+        assert_eq!(
+            echoes,
+            [
+                echo(1, 84, 50),
+                echo(2, 96, 25),
+                echo(1, 108, 13),
+                echo(2, 120, 6)
+            ]
+        );
+    }
+
+    fn note(note: u8) -> NoteEvent {
+        NoteEvent {
+            channel: 0,
+            note,
+            velocity: 100,
+        }
+    }
+
+    fn delayed(events: super::Echoes) -> Vec<(u64, MIDIEvent)> {
+        events
+            .into_iter()
+            .map(|(delay, event)| (delay, event.event))
+            .collect()
+    }
+
+    #[test]
+    fn note_offs_follow_their_repeat() {
+        let mut transform = DelayTransform::from_config(DelayTransformOptions {
+            time: Some(100),
+            subdivision: None,
+            repeats: Some(2),
+            decay: Some(0.5),
+            transpose: Some(12),
+            channels: None,
+            devices: None,
+        });
+        let key = ("keys".to_string(), 0, 60);
+        let echoes = transform.echoes("keys", &note(60));
+
+        // Every repeat ends right before the next one, even while the note is held
+        assert_eq!(
+            delayed(DelayTransform::play(&key, &echoes)),
+            [
+                (100, note_event(72, 50)),
+                (199, note_event(72, 0)),
+                (200, note_event(84, 25)),
+                (299, note_event(84, 0))
+            ]
+        );
+
+        // The tempo changes while the note is held
+        transform.time = 30;
+
+        // Let go of early, repeats get an earlier note off
+        assert_eq!(
+            delayed(DelayTransform::release(&key, 20, echoes.clone())),
+            [(100, note_event(72, 0)), (200, note_event(84, 0))]
+        );
+
+        // Held for longer than the time between repeats, their note offs are already on their way
+        assert_eq!(DelayTransform::release(&key, 150, echoes).len(), 0);
+    }
+
+    #[test]
+    fn repeats_of_the_held_key_wait_for_it_to_be_let_go_of() {
+        let transform = DelayTransform::from_config(DelayTransformOptions {
+            time: Some(100),
+            subdivision: None,
+            repeats: Some(3),
+            decay: Some(0.5),
+            transpose: None,
+            channels: None,
+            devices: None,
+        });
+        let key = ("keys".to_string(), 0, 60);
+        let echoes = transform.echoes("keys", &note(60));
+
+        assert_eq!(DelayTransform::play(&key, &echoes).len(), 0);
+
+        // The first repeat would have played while the key was held
+        assert_eq!(
+            delayed(DelayTransform::release(&key, 150, echoes)),
+            [
+                (50, note_event(60, 25)),
+                (149, note_event(60, 0)),
+                (150, note_event(60, 13)),
+                (249, note_event(60, 0))
+            ]
+        );
+    }
+}
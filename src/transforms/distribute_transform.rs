@@ -1,7 +1,7 @@
 use super::Transform;
 use crate::{
     iter_utils::{Cycle, CycleDirection},
-    midi_event::{MIDIEvent, MIDIRouterEvent, NoteEvent, Wrap},
+    midi_event::{MIDIEvent, MIDIRouterEvent, NoteEvent},
     scheduler::SchedulerHandler,
 };
 use schemars::JsonSchema;
@@ -10,11 +10,15 @@ use serde::Deserialize;
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct DistributeTransformOptions {
     between: Vec<u8>,
+    /// Device the notes are sent to. Defaults to the device they came from
+    output_device: Option<String>,
 }
 
 pub struct DistributeTransform {
-    pressed_keys: Vec<NoteEvent>,
+    // Keys being played, along with the device they're sent to
+    pressed_keys: Vec<(String, NoteEvent)>,
     between_iter: Cycle<u8>,
+    output_device: Option<String>,
 }
 
 impl DistributeTransform {
@@ -22,6 +26,7 @@ impl DistributeTransform {
         Self {
             between_iter: Cycle::new(config.between, CycleDirection::Forward, None),
             pressed_keys: vec![],
+            output_device: config.output_device,
         }
     }
 }
@@ -36,19 +41,35 @@ impl Transform for DistributeTransform {
             MIDIEvent::NoteOn(ref mut note) => {
                 note.channel = *self.between_iter.next();
 
-                self.pressed_keys.push(note.clone());
+                if let Some(device) = &self.output_device {
+                    message.device = device.clone();
+                }
+
+                self.pressed_keys
+                    .push((message.device.clone(), note.clone()));
 
                 Some(message)
             }
 
-            MIDIEvent::NoteOff(NoteEvent { note, .. }) => {
+            MIDIEvent::NoteOff(NoteEvent { note, velocity, .. }) => {
+                let device = self
+                    .output_device
+                    .clone()
+                    .unwrap_or_else(|| message.device.clone());
+
                 // Remove all keys with this note
-                self.pressed_keys.retain(|n| {
-                    let should_keep = n.note != note;
+                self.pressed_keys.retain(|(key_device, n)| {
+                    let should_keep = n.note != note || *key_device != device;
 
-                    // If this is to be removed, send a note off immediately
+                    // If this is to be removed, send a note off immediately, keeping its release velocity
                     if !should_keep {
-                        scheduler.send_now(n.get_note_off().wrap())
+                        scheduler.send_now(MIDIRouterEvent {
+                            device: key_device.clone(),
+                            event: MIDIEvent::NoteOff(NoteEvent {
+                                velocity,
+                                ..n.clone()
+                            }),
+                        })
                     }
 
                     should_keep
@@ -66,7 +87,7 @@ impl Transform for DistributeTransform {
 mod tests {
     use crate::{
         app::MIDIMapperEvent,
-        midi_event::{NoteEvent, Wrap},
+        midi_event::{MIDIEvent, MIDIRouterEvent, NoteEvent, Wrap},
         scheduler::Scheduler,
         transforms::Transform,
     };
@@ -76,6 +97,7 @@ mod tests {
     fn get_transform_instance() -> DistributeTransform {
         let config = DistributeTransformOptions {
             between: vec![9, 2, 4],
+            output_device: None,
         };
 
         DistributeTransform::from_config(config)
@@ -117,4 +139,38 @@ mod tests {
 
         assert_eq!(result, vec![9, 2, 4])
     }
+
+    #[test]
+    fn releases_notes_on_their_device() {
+        let mut transform = get_transform_instance();
+        let (scheduler, scheduler_handler) = Scheduler::new();
+        let mut play = |velocity| {
+            transform.process_message(
+                MIDIMapperEvent::RouterMessage(MIDIRouterEvent {
+                    device: "keys".into(),
+                    ..NoteEvent {
+                        channel: 3,
+                        note: 60,
+                        velocity,
+                    }
+                    .wrap()
+                }),
+                &scheduler_handler,
+            )
+        };
+
+        assert_eq!(play(100).unwrap().device, "keys");
+        assert_eq!(play(0), None);
+        assert_eq!(
+            scheduler.receiver.drain().collect::<Vec<_>>(),
+            [MIDIRouterEvent {
+                device: "keys".into(),
+                event: MIDIEvent::NoteOff(NoteEvent {
+                    channel: 9,
+                    note: 60,
+                    velocity: 0,
+                }),
+            }]
+        );
+    }
 }
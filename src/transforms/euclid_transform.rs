@@ -0,0 +1,294 @@
+use super::Transform;
+use crate::{
+    midi_event::{MIDIEvent, MIDIRouterEvent, Note, NoteEvent},
+    scheduler::SchedulerHandler,
+    tempo::StepTimer,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EuclidNoteOptions {
+    /// e.g. `C1`
+    note: Note,
+    /// Defaults to 100
+    velocity: Option<u8>,
+    /// Defaults to 0
+    channel: Option<u8>,
+    output_device: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EuclidTransformOptions {
+    subdivision: f64,
+    /// Number of hits spread as evenly as possible over the steps
+    hits: u8,
+    steps: u8,
+    /// Steps the pattern is shifted to the right by. Defaults to 0
+    rotation: Option<u8>,
+    /// Note played on every hit. Incoming notes are held and played on every hit instead when
+    /// not set
+    note: Option<EuclidNoteOptions>,
+    /// Milliseconds every hit lasts, cut short to end before the next step. Defaults to 100
+    note_duration: Option<u64>,
+    /// Sets the hits from this controller, its values spread between 0 and the number of steps
+    hits_controller: Option<u8>,
+    /// Sets the steps from this controller, its values spread between 1 and 32
+    steps_controller: Option<u8>,
+    /// Sets the rotation from this controller, its values spread over the number of steps
+    rotation_controller: Option<u8>,
+}
+
+pub struct EuclidTransform {
+    subdivision: f64,
+    hits: u8,
+    steps: u8,
+    rotation: u8,
+    pattern: Vec<bool>,
+    position: usize,
+    // Note of our own, along with the device it is sent to
+    note: Option<(String, NoteEvent)>,
+    note_duration: u64,
+    step_timer: StepTimer,
+    hits_controller: Option<u8>,
+    steps_controller: Option<u8>,
+    rotation_controller: Option<u8>,
+    // Incoming notes gated by the pattern, along with the device they came from
+    held_notes: Vec<(String, NoteEvent)>,
+}
+
+impl EuclidTransform {
+    pub fn from_config(options: EuclidTransformOptions) -> Self {
+        let note = options.note.map(|note| {
+            (
+                note.output_device,
+                NoteEvent {
+                    channel: note.channel.unwrap_or(0),
+                    note: note.note.into(),
+                    velocity: note.velocity.unwrap_or(100).clamp(1, 127),
+                },
+            )
+        });
+
+        let mut transform = Self {
+            subdivision: options.subdivision,
+            hits: options.hits,
+            steps: options.steps.max(1),
+            rotation: options.rotation.unwrap_or(0),
+            pattern: vec![],
+            position: 0,
+            note,
+            note_duration: options.note_duration.unwrap_or(100),
+            step_timer: StepTimer::default(),
+            hits_controller: options.hits_controller,
+            steps_controller: options.steps_controller,
+            rotation_controller: options.rotation_controller,
+            held_notes: vec![],
+        };
+        transform.update_pattern();
+
+        transform
+    }
+
+    /// Spreads the hits over the steps as evenly as possible, starting with a hit, then shifts
+    /// them to the right by the rotation
+    fn pattern(hits: u8, steps: u8, rotation: u8) -> Vec<bool> {
+        let (hits, steps) = (hits.min(steps) as usize, steps as usize);
+
+        (0..steps)
+            .map(|step| (step + steps - rotation as usize % steps) % steps)
+            .map(|step| step * hits % steps < hits)
+            .collect()
+    }
+
+    fn update_pattern(&mut self) {
+        self.pattern = Self::pattern(self.hits, self.steps, self.rotation);
+        self.position %= self.pattern.len();
+    }
+
+    /// Milliseconds the next hit lasts, ending before the following step so that hits of the same
+    /// note don't cut each other off
+    fn hit_duration(&self) -> u64 {
+        match self.step_timer.step() {
+            Some(step_duration) => {
+                let step = step_duration.as_millis() as u64;
+
+                self.note_duration.min(step.saturating_sub(1)).max(1)
+            }
+            None => self.note_duration,
+        }
+    }
+
+    /// Updates the parameter modulated by this controller, if any
+    fn modulate(&mut self, controller: u8, value: u8) -> bool {
+        let scale = |max: u8| (value as u16 * (max as u16 + 1) / 128) as u8;
+
+        if Some(controller) == self.hits_controller {
+            self.hits = scale(self.steps);
+        } else if Some(controller) == self.steps_controller {
+            self.steps = scale(31) + 1;
+        } else if Some(controller) == self.rotation_controller {
+            self.rotation = scale(self.steps - 1);
+        } else {
+            return false;
+        }
+
+        self.update_pattern();
+
+        true
+    }
+}
+
+impl Transform for EuclidTransform {
+    fn get_tempo_subdiv(&self) -> Option<f64> {
+        Some(self.subdivision)
+    }
+
+    fn on_tick(&mut self, scheduler: &SchedulerHandler) -> Option<MIDIRouterEvent> {
+        self.step_timer.tick();
+
+        let is_hit = self.pattern[self.position];
+        self.position = (self.position + 1) % self.pattern.len();
+
+        if !is_hit {
+            return None;
+        }
+
+        let notes = match &self.note {
+            Some(note) => vec![note.clone()],
+            None => self.held_notes.clone(),
+        };
+        let duration = self.hit_duration();
+
+        notes.into_iter().for_each(|(device, note)| {
+            scheduler.send_later(
+                MIDIRouterEvent {
+                    device: device.clone(),
+                    event: MIDIEvent::NoteOff(note.get_note_off()),
+                },
+                duration,
+            );
+            scheduler.send_now(MIDIRouterEvent {
+                device,
+                event: MIDIEvent::NoteOn(note),
+            });
+        });
+
+        None
+    }
+
+    fn on_message(
+        &mut self,
+        message: MIDIRouterEvent,
+        _scheduler: &SchedulerHandler,
+    ) -> Option<MIDIRouterEvent> {
+        match &message.event {
+            MIDIEvent::Controller(controller)
+                if self.modulate(controller.controller, controller.value) =>
+            {
+                None
+            }
+
+            // Incoming notes are gated when there is no note of our own to play
+            MIDIEvent::NoteOn(note) if self.note.is_none() => {
+                self.held_notes.push((message.device.clone(), note.clone()));
+
+                None
+            }
+            MIDIEvent::NoteOff(note) if self.note.is_none() => {
+                self.held_notes.retain(|(device, held)| {
+                    *device != message.device
+                        || held.note != note.note
+                        || held.channel != note.channel
+                });
+
+                None
+            }
+
+            _ => Some(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EuclidNoteOptions, EuclidTransform, EuclidTransformOptions};
+    use crate::{
+        app::MIDIMapperEvent,
+        midi_event::{Controller, MIDIEvent, MIDIRouterEvent, Note},
+        scheduler::Scheduler,
+        transforms::Transform,
+    };
+    use std::time::Duration;
+
+    fn pattern(hits: u8, steps: u8, rotation: u8) -> String {
+        EuclidTransform::pattern(hits, steps, rotation)
+            .into_iter()
+            .map(|hit| if hit { 'x' } else { '.' })
+            .collect()
+    }
+
+    #[test]
+    fn spreads_hits_evenly() {
+        assert_eq!(pattern(3, 8, 0), "x..x..x.");
+        assert_eq!(pattern(3, 8, 1), ".x..x..x");
+        assert_eq!(pattern(5, 8, 0), "x.x.xx.x");
+        assert_eq!(pattern(4, 4, 2), "xxxx");
+        assert_eq!(pattern(0, 4, 0), "....");
+    }
+
+    fn options() -> EuclidTransformOptions {
+        EuclidTransformOptions {
+            subdivision: 0.25,
+            hits: 2,
+            steps: 8,
+            rotation: None,
+            note: Some(EuclidNoteOptions {
+                note: Note::C1,
+                velocity: None,
+                channel: None,
+                output_device: "drums".into(),
+            }),
+            note_duration: None,
+            hits_controller: None,
+            steps_controller: None,
+            rotation_controller: None,
+        }
+    }
+
+    #[test]
+    fn modulates_from_controllers() {
+        let mut transform = EuclidTransform::from_config(EuclidTransformOptions {
+            hits_controller: Some(20),
+            ..options()
+        });
+        let (_scheduler, scheduler_handler) = Scheduler::new();
+
+        let result = transform.process_message(
+            MIDIMapperEvent::RouterMessage(MIDIRouterEvent {
+                device: "knobs".into(),
+                event: MIDIEvent::Controller(Controller {
+                    channel: 0,
+                    controller: 20,
+                    value: 127,
+                }),
+            }),
+            &scheduler_handler,
+        );
+
+        assert_eq!(result, None);
+        assert_eq!(transform.pattern, [true; 8]);
+    }
+
+    #[test]
+    fn ends_hits_before_the_next_step() {
+        let mut transform = EuclidTransform::from_config(options());
+        assert_eq!(transform.hit_duration(), 100);
+
+        transform.step_timer.set_step(Duration::from_millis(60));
+        assert_eq!(transform.hit_duration(), 59);
+
+        transform.step_timer.set_step(Duration::from_millis(500));
+        assert_eq!(transform.hit_duration(), 100);
+    }
+}
@@ -1,71 +1,160 @@
+use std::collections::HashMap;
+
 use super::Transform;
 use crate::{
-    midi_event::{MIDIEventIdentity, MIDIRouterEvent},
+    midi_event::{MIDIEvent, MIDIEventIdentity, MIDIRouterEvent, Note},
     scheduler::SchedulerHandler,
 };
 use schemars::JsonSchema;
 use serde::Deserialize;
 
+/// Every given predicate must match for an event to pass. Events lacking the property a predicate
+/// looks at (e.g. `velocities` for a controller) don't match it, except for note offs which are
+/// given the velocity of their note on
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct FilterTransformOptions {
     pub devices: Option<Vec<String>>,
     pub channels: Option<Vec<u8>>,
     pub event_types: Option<Vec<MIDIEventIdentity>>,
+    /// Lowest and highest note, inclusive, e.g. `["C2", "B4"]`
+    pub notes: Option<(Note, Note)>,
+    /// Lowest and highest note on velocity, inclusive
+    pub velocities: Option<(u8, u8)>,
+    /// Controller numbers
+    pub controllers: Option<Vec<u8>>,
+    /// Lowest and highest controller value, inclusive
+    pub values: Option<(u8, u8)>,
+    /// Program change numbers
+    pub programs: Option<Vec<u8>>,
+    /// Drops events matching this filter
+    pub exclude: Option<Box<FilterTransformOptions>>,
+    /// At least one of these filters must match
+    pub any: Option<Vec<FilterTransformOptions>>,
+    /// All of these filters must match
+    pub all: Option<Vec<FilterTransformOptions>>,
 }
 
-pub struct FilterTransform {
+struct Filter {
     devices: Vec<String>,
     channels: Vec<u8>,
     event_types: Vec<MIDIEventIdentity>,
+    notes: Option<(u8, u8)>,
+    velocities: Option<(u8, u8)>,
+    controllers: Vec<u8>,
+    values: Option<(u8, u8)>,
+    programs: Vec<u8>,
+    exclude: Option<Box<Filter>>,
+    any: Vec<Filter>,
+    all: Vec<Filter>,
 }
 
-impl FilterTransform {
-    pub fn from_config(options: FilterTransformOptions) -> Self {
+impl Filter {
+    fn from_config(options: FilterTransformOptions) -> Self {
         Self {
             devices: options.devices.unwrap_or(vec![]),
             channels: options.channels.unwrap_or(vec![]),
             event_types: options.event_types.unwrap_or(vec![]),
+            notes: options.notes.map(|(low, high)| (low.into(), high.into())),
+            velocities: options.velocities,
+            controllers: options.controllers.unwrap_or(vec![]),
+            values: options.values,
+            programs: options.programs.unwrap_or(vec![]),
+            exclude: options
+                .exclude
+                .map(|exclude| Box::new(Self::from_config(*exclude))),
+            any: options
+                .any
+                .unwrap_or(vec![])
+                .into_iter()
+                .map(Self::from_config)
+                .collect(),
+            all: options
+                .all
+                .unwrap_or(vec![])
+                .into_iter()
+                .map(Self::from_config)
+                .collect(),
         }
     }
+
+    // `velocity` is the note on velocity the event is judged by, for note offs that of their note on
+    fn matches(&self, e: &MIDIRouterEvent, velocity: Option<u8>) -> bool {
+        let in_range = |range: Option<(u8, u8)>, value: Option<u8>| match (range, value) {
+            (None, _) => true,
+            (Some((low, high)), Some(value)) => (low..=high).contains(&value),
+            (Some(_), None) => false,
+        };
+        let in_list = |list: &Vec<u8>, value: Option<u8>| {
+            list.is_empty() || value.is_some_and(|value| list.contains(&value))
+        };
+
+        let note = match &e.event {
+            MIDIEvent::NoteOn(note) | MIDIEvent::NoteOff(note) => Some(note.note),
+            MIDIEvent::PolyphonicPressure(pressure) => Some(pressure.note),
+            _ => None,
+        };
+        let (controller, value) = match &e.event {
+            MIDIEvent::Controller(controller) => {
+                (Some(controller.controller), Some(controller.value))
+            }
+            _ => (None, None),
+        };
+        let program = match &e.event {
+            MIDIEvent::ProgramChange(program) => Some(program.program),
+            _ => None,
+        };
+
+        (self.devices.is_empty() || self.devices.contains(&e.device))
+            && (self.event_types.is_empty() || self.event_types.contains(&e.event.get_identity()))
+            && (self.channels.is_empty() || self.channels.contains(&e.event.get_channel()))
+            && in_range(self.notes, note)
+            && in_range(self.velocities, velocity)
+            && in_list(&self.controllers, controller)
+            && in_range(self.values, value)
+            && in_list(&self.programs, program)
+            && !self
+                .exclude
+                .as_ref()
+                .is_some_and(|exclude| exclude.matches(e, velocity))
+            && (self.any.is_empty() || self.any.iter().any(|filter| filter.matches(e, velocity)))
+            && self.all.iter().all(|filter| filter.matches(e, velocity))
+    }
+}
+
+pub struct FilterTransform {
+    filter: Filter,
+    // Velocity of every held note, so its note off is judged the same way as its note on
+    held_notes: HashMap<(String, u8, u8), u8>,
 }
 
 impl FilterTransform {
-    fn should_pass(&self, midi_router_event: MIDIRouterEvent) -> Option<MIDIRouterEvent> {
-        let checks: Vec<fn(&Self, &MIDIRouterEvent) -> bool> = vec![
-            |s, e| {
-                if s.devices.is_empty() {
-                    return true;
-                }
-
-                s.devices.contains(&e.device)
-            },
-            |s, e| {
-                if s.event_types.is_empty() {
-                    return true;
-                }
-
-                s.event_types.contains(&e.event.get_identity())
-            },
-            |s, e| {
-                if s.channels.is_empty() {
-                    return true;
-                }
-
-                s.channels.contains(&e.event.get_channel())
-            },
-        ];
-
-        // If any of the checks return false, this will stop iterating
-        let disallowed = checks.iter().any(|check| !check(self, &midi_router_event));
-
-        if disallowed {
-            None
-        } else {
-            Some(midi_router_event)
+    pub fn from_config(options: FilterTransformOptions) -> Self {
+        Self {
+            filter: Filter::from_config(options),
+            held_notes: HashMap::new(),
         }
     }
 }
 
+impl FilterTransform {
+    fn should_pass(&mut self, midi_router_event: MIDIRouterEvent) -> Option<MIDIRouterEvent> {
+        let device = midi_router_event.device.clone();
+        let velocity = match &midi_router_event.event {
+            MIDIEvent::NoteOn(note) => {
+                self.held_notes
+                    .insert((device, note.channel, note.note), note.velocity);
+
+                Some(note.velocity)
+            }
+            MIDIEvent::NoteOff(note) => self.held_notes.remove(&(device, note.channel, note.note)),
+            _ => None,
+        };
+        let allowed = self.filter.matches(&midi_router_event, velocity);
+
+        allowed.then_some(midi_router_event)
+    }
+}
+
 impl Transform for FilterTransform {
     fn on_message(
         &mut self,
@@ -75,3 +164,142 @@ impl Transform for FilterTransform {
         self.should_pass(message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{FilterTransform, FilterTransformOptions};
+    use crate::midi_event::{
+        note_event, Controller, MIDIEvent, MIDIEventIdentity, MIDIRouterEvent, Note,
+    };
+
+    fn options() -> FilterTransformOptions {
+        FilterTransformOptions {
+            devices: None,
+            channels: None,
+            event_types: None,
+            notes: None,
+            velocities: None,
+            controllers: None,
+            values: None,
+            programs: None,
+            exclude: None,
+            any: None,
+            all: None,
+        }
+    }
+
+    fn note(device: &str, note: u8, velocity: u8) -> MIDIRouterEvent {
+        MIDIRouterEvent {
+            device: device.into(),
+            event: note_event(note, velocity),
+        }
+    }
+
+    fn cc(device: &str, controller: u8, value: u8) -> MIDIRouterEvent {
+        MIDIRouterEvent {
+            device: device.into(),
+            event: MIDIEvent::Controller(Controller {
+                channel: 0,
+                controller,
+                value,
+            }),
+        }
+    }
+
+    fn passes(transform: &mut FilterTransform, events: Vec<MIDIRouterEvent>) -> Vec<bool> {
+        events
+            .into_iter()
+            .map(|event| transform.should_pass(event).is_some())
+            .collect()
+    }
+
+    #[test]
+    fn filters_by_note_and_controller() {
+        let mut transform = FilterTransform::from_config(FilterTransformOptions {
+            any: Some(vec![
+                FilterTransformOptions {
+                    notes: Some((Note::C4, Note::B4)),
+                    ..options()
+                },
+                FilterTransformOptions {
+                    controllers: Some(vec![7]),
+                    values: Some((10, 100)),
+                    ..options()
+                },
+            ]),
+            ..options()
+        });
+
+        assert_eq!(
+            passes(
+                &mut transform,
+                vec![
+                    note("keys", 60, 100),
+                    note("keys", 72, 100),
+                    cc("keys", 7, 50),
+                    cc("keys", 7, 120),
+                    cc("keys", 1, 50),
+                ]
+            ),
+            vec![true, false, true, false, false]
+        );
+    }
+
+    #[test]
+    fn excludes_matching_events() {
+        let mut transform = FilterTransform::from_config(FilterTransformOptions {
+            exclude: Some(Box::new(FilterTransformOptions {
+                devices: Some(vec!["keys".into()]),
+                controllers: Some(vec![1]),
+                ..options()
+            })),
+            ..options()
+        });
+
+        assert_eq!(
+            passes(
+                &mut transform,
+                vec![cc("keys", 1, 64), cc("pads", 1, 64), cc("keys", 2, 64)]
+            ),
+            vec![false, true, true]
+        );
+    }
+
+    #[test]
+    fn note_offs_follow_their_note_on() {
+        let mut transform = FilterTransform::from_config(FilterTransformOptions {
+            velocities: Some((64, 127)),
+            ..options()
+        });
+
+        assert_eq!(
+            passes(
+                &mut transform,
+                vec![
+                    note("keys", 60, 100),
+                    note("keys", 62, 10),
+                    note("keys", 60, 0),
+                    note("keys", 62, 0),
+                ]
+            ),
+            vec![true, false, true, false]
+        );
+    }
+
+    #[test]
+    fn note_offs_still_need_their_event_type() {
+        let mut transform = FilterTransform::from_config(FilterTransformOptions {
+            event_types: Some(vec![MIDIEventIdentity::NoteOn]),
+            velocities: Some((64, 127)),
+            ..options()
+        });
+
+        assert_eq!(
+            passes(
+                &mut transform,
+                vec![note("keys", 60, 100), note("keys", 60, 0)]
+            ),
+            vec![true, false]
+        );
+    }
+}
@@ -2,16 +2,24 @@ use schemars::JsonSchema;
 use serde::Deserialize;
 
 use super::Transform;
-use crate::{midi_event::MIDIRouterEvent, scheduler::SchedulerHandler};
+use crate::{
+    midi_event::{MIDIRouterEvent, Note},
+    note_ledger::NoteLedgerHandle,
+    scheduler::SchedulerHandler,
+};
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct InspectTransformOptions {
     pub prefix: Option<String>,
+    /// Also print the notes currently sounding on the output devices
+    pub sounding_notes: Option<bool>,
 }
 
 // A helper transform that prints whatever it receives, with an optional prefix
 pub struct InspectTransform {
     pub prefix: String,
+    sounding_notes: bool,
+    note_ledger: Option<NoteLedgerHandle>,
 }
 
 impl InspectTransform {
@@ -21,11 +29,17 @@ impl InspectTransform {
                 .prefix
                 .map(|prefix| format!("[{prefix}]"))
                 .unwrap_or("".into()),
+            sounding_notes: options.sounding_notes.unwrap_or(false),
+            note_ledger: None,
         }
     }
 }
 
 impl Transform for InspectTransform {
+    fn set_note_ledger(&mut self, note_ledger: NoteLedgerHandle) {
+        self.note_ledger = Some(note_ledger);
+    }
+
     fn on_message(
         &mut self,
         v: MIDIRouterEvent,
@@ -33,6 +47,19 @@ impl Transform for InspectTransform {
     ) -> Option<MIDIRouterEvent> {
         println!("{}{}", self.prefix, v);
 
+        if let (true, Some(note_ledger)) = (self.sounding_notes, &self.note_ledger) {
+            let sounding = note_ledger
+                .lock()
+                .unwrap()
+                .sounding()
+                .iter()
+                .map(|n| format!("{} ch{} {}", n.device, n.channel + 1, Note::from(n.note)))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            println!("{}  sounding: [{}]", self.prefix, sounding);
+        }
+
         Some(v)
     }
 }
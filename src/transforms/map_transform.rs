@@ -5,26 +5,145 @@ use serde::Deserialize;
 
 use super::Transform;
 use crate::{
-    midi_event::{MIDIEvent, MIDIRouterEvent},
+    curve::Curve,
+    midi_event::{
+        ChannelPressure, Controller, MIDIEvent, MIDIRouterEvent, PitchBend, ProgramChange,
+    },
     scheduler::SchedulerHandler,
 };
 
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub enum MapTarget {
+    Controller(u8),
+    PitchBend,
+    ChannelPressure,
+    ProgramChange,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ValueMappingOptions {
+    /// Incoming controller whose value is mapped
+    pub cc: u8,
+    /// Lowest and highest incoming value, anything outside is clamped. Defaults to `[0, 127]`
+    pub input_range: Option<(u8, u8)>,
+    /// Lowest and highest outgoing value. Defaults to the full range of the target, i.e.
+    /// `[-8192, 8191]` for pitch bend and `[0, 127]` for everything else
+    pub output_range: Option<(i16, i16)>,
+    pub invert: Option<bool>,
+    pub curve: Option<Curve>,
+    /// Number of evenly spaced values the output is snapped to
+    pub steps: Option<u16>,
+    /// Message sent with the mapped value. Defaults to the controller `cc` renumbers it to, or
+    /// the same controller
+    pub target: Option<MapTarget>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct MapTransformOptions {
     pub channels: Option<Vec<(u8, u8)>>,
     pub cc: Option<Vec<(u8, u8)>>,
+    pub values: Option<Vec<ValueMappingOptions>>,
+}
+
+struct ValueMapping {
+    input_range: (u8, u8),
+    output_range: (i16, i16),
+    invert: bool,
+    curve: Curve,
+    steps: Option<u16>,
+    target: Option<MapTarget>,
+}
+
+impl ValueMapping {
+    fn from_config(options: ValueMappingOptions) -> Self {
+        let output_range = match options.target {
+            Some(MapTarget::PitchBend) => (-8192, 8191),
+            _ => (0, 127),
+        };
+
+        Self {
+            input_range: options.input_range.unwrap_or((0, 127)),
+            output_range: options.output_range.unwrap_or(output_range),
+            invert: options.invert.unwrap_or(false),
+            curve: options.curve.unwrap_or_default(),
+            steps: options.steps,
+            target: options.target,
+        }
+    }
+
+    fn map_value(&self, value: u8) -> i16 {
+        let (low, high) = self.input_range;
+        let span = (high as f64 - low as f64).max(1.0);
+        let mut x = ((value as f64 - low as f64) / span).clamp(0.0, 1.0);
+
+        if self.invert {
+            x = 1.0 - x;
+        }
+
+        x = self.curve.apply(x);
+
+        if let Some(steps) = self.steps.filter(|steps| *steps > 1) {
+            let steps = (steps - 1) as f64;
+            x = (x * steps).round() / steps;
+        }
+
+        let (low, high) = self.output_range;
+        (low as f64 + x * (high as f64 - low as f64)).round() as i16
+    }
+
+    fn map(&self, controller: &Controller) -> MIDIEvent {
+        let value = self.map_value(controller.value);
+        let channel = controller.channel;
+        let as_u8 = value.clamp(0, 127) as u8;
+
+        match self.target {
+            None => MIDIEvent::Controller(Controller {
+                value: as_u8,
+                ..controller.clone()
+            }),
+            Some(MapTarget::Controller(target)) => MIDIEvent::Controller(Controller {
+                channel,
+                controller: target,
+                value: as_u8,
+            }),
+            Some(MapTarget::PitchBend) => MIDIEvent::PitchBend(PitchBend::new(channel, value)),
+            Some(MapTarget::ChannelPressure) => MIDIEvent::ChannelPressure(ChannelPressure {
+                channel,
+                pressure: as_u8,
+            }),
+            Some(MapTarget::ProgramChange) => MIDIEvent::ProgramChange(ProgramChange {
+                channel,
+                program: as_u8,
+            }),
+        }
+    }
 }
 
 pub struct MapTransform {
     channels: HashMap<u8, u8>,
     cc: HashMap<u8, u8>,
+    values: HashMap<u8, ValueMapping>,
 }
 
 impl MapTransform {
     pub fn from_config(options: MapTransformOptions) -> Self {
+        let cc: HashMap<u8, u8> = HashMap::from_iter(options.cc.unwrap_or_default());
+
         Self {
             channels: HashMap::from_iter(options.channels.unwrap_or_default()),
-            cc: HashMap::from_iter(options.cc.unwrap_or_default()),
+            values: options
+                .values
+                .unwrap_or_default()
+                .into_iter()
+                .map(|mut mapping| {
+                    if mapping.target.is_none() {
+                        mapping.target = cc.get(&mapping.cc).copied().map(MapTarget::Controller);
+                    }
+
+                    (mapping.cc, ValueMapping::from_config(mapping))
+                })
+                .collect(),
+            cc,
         }
     }
 }
@@ -42,13 +161,134 @@ impl Transform for MapTransform {
             message.event.set_channel(*target_channel);
         }
 
-        // Map CCs
         if let MIDIEvent::Controller(ref mut controller) = message.event {
-            if let Some(target_cc) = self.cc.get(&controller.controller) {
-                controller.controller = *target_cc;
+            match self.values.get(&controller.controller) {
+                // Map CC values, possibly into a different kind of message. The mapping already
+                // picked its target, so it isn't renumbered again
+                Some(mapping) => message.event = mapping.map(controller),
+                // Map CCs
+                None => {
+                    if let Some(target_cc) = self.cc.get(&controller.controller) {
+                        controller.controller = *target_cc;
+                    }
+                }
             }
         }
 
         Some(message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{MapTarget, MapTransform, MapTransformOptions, ValueMappingOptions};
+    use crate::{
+        curve::Curve,
+        midi_event::{ChannelPressure, Controller, MIDIEvent, MIDIRouterEvent, PitchBend},
+        scheduler::Scheduler,
+        transforms::Transform,
+    };
+
+    fn mapping(cc: u8) -> ValueMappingOptions {
+        ValueMappingOptions {
+            cc,
+            input_range: None,
+            output_range: None,
+            invert: None,
+            curve: None,
+            steps: None,
+            target: None,
+        }
+    }
+
+    fn map(transform: &mut MapTransform, controller: u8, value: u8) -> MIDIEvent {
+        let (_scheduler, scheduler_handler) = Scheduler::new();
+
+        transform
+            .on_message(
+                MIDIRouterEvent {
+                    device: "pedal".into(),
+                    event: MIDIEvent::Controller(Controller {
+                        channel: 0,
+                        controller,
+                        value,
+                    }),
+                },
+                &scheduler_handler,
+            )
+            .unwrap()
+            .event
+    }
+
+    fn cc(controller: u8, value: u8) -> MIDIEvent {
+        MIDIEvent::Controller(Controller {
+            channel: 0,
+            controller,
+            value,
+        })
+    }
+
+    #[test]
+    fn scales_inverts_and_steps_values() {
+        let mut transform = MapTransform::from_config(MapTransformOptions {
+            channels: None,
+            cc: Some(vec![(1, 2), (2, 3)]),
+            values: Some(vec![
+                ValueMappingOptions {
+                    input_range: Some((20, 100)),
+                    output_range: Some((0, 64)),
+                    ..mapping(1)
+                },
+                ValueMappingOptions {
+                    invert: Some(true),
+                    steps: Some(3),
+                    curve: Some(Curve::Linear),
+                    ..mapping(7)
+                },
+            ]),
+        });
+
+        assert_eq!(map(&mut transform, 1, 10), cc(2, 0));
+        assert_eq!(map(&mut transform, 1, 60), cc(2, 32));
+        assert_eq!(map(&mut transform, 1, 127), cc(2, 64));
+        assert_eq!(map(&mut transform, 2, 127), cc(3, 127));
+        assert_eq!(map(&mut transform, 7, 0), cc(7, 127));
+        assert_eq!(map(&mut transform, 7, 70), cc(7, 64));
+        assert_eq!(map(&mut transform, 7, 120), cc(7, 0));
+        assert_eq!(map(&mut transform, 8, 120), cc(8, 120));
+    }
+
+    #[test]
+    fn maps_to_other_messages() {
+        let mut transform = MapTransform::from_config(MapTransformOptions {
+            channels: None,
+            cc: None,
+            values: Some(vec![
+                ValueMappingOptions {
+                    target: Some(MapTarget::ChannelPressure),
+                    ..mapping(11)
+                },
+                ValueMappingOptions {
+                    target: Some(MapTarget::PitchBend),
+                    ..mapping(1)
+                },
+            ]),
+        });
+
+        assert_eq!(
+            map(&mut transform, 11, 90),
+            MIDIEvent::ChannelPressure(ChannelPressure {
+                channel: 0,
+                pressure: 90
+            })
+        );
+        assert_eq!(
+            map(&mut transform, 1, 0),
+            MIDIEvent::PitchBend(PitchBend::new(0, -8192))
+        );
+        assert_eq!(
+            map(&mut transform, 1, 127),
+            MIDIEvent::PitchBend(PitchBend::new(0, 8191))
+        );
+    }
+}
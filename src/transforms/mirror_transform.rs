@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use schemars::JsonSchema;
 use serde::Deserialize;
 
@@ -31,7 +29,7 @@ impl Transform for MirrorTransform {
     ) -> Option<MIDIRouterEvent> {
         self.channels.iter().for_each(|channel| {
             let mut current = message.clone();
-            current.event.set_channel(channel.clone());
+            current.event.set_channel(*channel);
             scheduler.send_now(current)
         });
 
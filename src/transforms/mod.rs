@@ -1,19 +1,45 @@
 pub mod arpeggio_transform;
+pub mod bend_transform;
+pub mod chord_transform;
+pub mod convert_transform;
+pub mod delay_transform;
 pub mod distribute_transform;
+pub mod euclid_transform;
 pub mod filter_transform;
 pub mod inspect_transform;
 pub mod map_transform;
 pub mod mirror_transform;
 pub mod output_transform;
+pub mod quantize_transform;
+pub mod random_transform;
+pub mod ratchet_transform;
+pub mod sequencer_transform;
+pub mod split_transform;
+pub mod strum_transform;
 pub mod transform;
+pub mod transpose_transform;
+pub mod velocity_transform;
 pub mod wasm_transform;
 
 pub use arpeggio_transform::ArpeggioTransform;
+pub use bend_transform::BendTransform;
+pub use chord_transform::ChordTransform;
+pub use convert_transform::ConvertTransform;
+pub use delay_transform::DelayTransform;
 pub use distribute_transform::DistributeTransform;
+pub use euclid_transform::EuclidTransform;
 pub use filter_transform::{FilterTransform, FilterTransformOptions};
 pub use inspect_transform::InspectTransform;
 pub use map_transform::{MapTransform, MapTransformOptions};
 pub use mirror_transform::MirrorTransform;
 pub use output_transform::OutputTransform;
+pub use quantize_transform::QuantizeTransform;
+pub use random_transform::RandomTransform;
+pub use ratchet_transform::RatchetTransform;
+pub use sequencer_transform::SequencerTransform;
+pub use split_transform::SplitTransform;
+pub use strum_transform::StrumTransform;
 pub use transform::Transform;
+pub use transpose_transform::TransposeTransform;
+pub use velocity_transform::VelocityTransform;
 pub use wasm_transform::WasmTransform;
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::Transform;
+use crate::{
+    midi_event::{MIDIEvent, MIDIRouterEvent},
+    scale::{Rounding, Scale, ScaleOptions},
+    scheduler::SchedulerHandler,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct QuantizeTransformOptions {
+    #[serde(flatten)]
+    pub scale: ScaleOptions,
+    pub rounding: Option<Rounding>,
+    /// Drop notes that don't belong to the scale instead of moving them
+    pub drop_out_of_scale: Option<bool>,
+}
+
+pub struct QuantizeTransform {
+    scale: Scale,
+    rounding: Rounding,
+    drop_out_of_scale: bool,
+    // Quantized note for every note on we've let through (None if it was dropped)
+    sounding: HashMap<(String, u8, u8), Option<u8>>,
+    // How many incoming notes are holding each quantized note, as several can snap to the same one
+    held: HashMap<(String, u8, u8), usize>,
+}
+
+impl QuantizeTransform {
+    pub fn from_config(options: QuantizeTransformOptions) -> Self {
+        Self {
+            scale: Scale::from_config(options.scale),
+            rounding: options.rounding.unwrap_or_default(),
+            drop_out_of_scale: options.drop_out_of_scale.unwrap_or(false),
+            sounding: HashMap::new(),
+            held: HashMap::new(),
+        }
+    }
+
+    fn quantize(&self, note: u8) -> Option<u8> {
+        if self.drop_out_of_scale {
+            self.scale.contains(note).then_some(note)
+        } else {
+            self.scale.snap(note, self.rounding)
+        }
+    }
+}
+
+impl Transform for QuantizeTransform {
+    fn on_message(
+        &mut self,
+        mut message: MIDIRouterEvent,
+        _scheduler: &SchedulerHandler,
+    ) -> Option<MIDIRouterEvent> {
+        let device = message.device.clone();
+
+        let target = match message.event {
+            MIDIEvent::NoteOn(ref mut note) => {
+                let target = self.quantize(note.note);
+                self.sounding
+                    .insert((device.clone(), note.channel, note.note), target);
+
+                target.map(|target| {
+                    *self.held.entry((device, note.channel, target)).or_default() += 1;
+                    note.note = target;
+                })
+            }
+
+            MIDIEvent::NoteOff(ref mut note) => {
+                let target = self
+                    .sounding
+                    .remove(&(device.clone(), note.channel, note.note))
+                    .unwrap_or_else(|| self.quantize(note.note));
+
+                // Only release the quantized note once nothing else is holding it
+                target.and_then(|target| {
+                    note.note = target;
+
+                    let key = (device, note.channel, target);
+                    match self.held.get_mut(&key) {
+                        Some(count) if *count > 1 => {
+                            *count -= 1;
+                            None
+                        }
+                        _ => {
+                            self.held.remove(&key);
+                            Some(())
+                        }
+                    }
+                })
+            }
+
+            MIDIEvent::PolyphonicPressure(ref mut pressure) => {
+                let target = self
+                    .sounding
+                    .get(&(device, pressure.channel, pressure.note))
+                    .copied()
+                    .unwrap_or_else(|| self.quantize(pressure.note));
+
+                target.map(|target| pressure.note = target)
+            }
+
+            _ => Some(()),
+        };
+
+        target.map(|_| message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QuantizeTransform, QuantizeTransformOptions};
+    use crate::{
+        app::MIDIMapperEvent,
+        midi_event::{note_event as note, MIDIEvent, MIDIRouterEvent},
+        scale::{Rounding, ScaleOptions},
+        scheduler::Scheduler,
+        transforms::Transform,
+    };
+
+    #[test]
+    fn releases_shared_notes_once_every_key_is_up() {
+        let mut transform = QuantizeTransform::from_config(QuantizeTransformOptions {
+            scale: ScaleOptions {
+                root: "C".parse().unwrap(),
+                mode: None,
+                pitch_classes: None,
+            },
+            rounding: Some(Rounding::Down),
+            drop_out_of_scale: None,
+        });
+        let (_scheduler, scheduler_handler) = Scheduler::new();
+        let mut play = |event: MIDIEvent| {
+            transform
+                .process_message(
+                    MIDIMapperEvent::RouterMessage(MIDIRouterEvent {
+                        device: "keys".into(),
+                        event,
+                    }),
+                    &scheduler_handler,
+                )
+                .map(|message| message.event)
+        };
+
+        // C4 and C#4 both snap to C4
+        assert_eq!(play(note(60, 100)), Some(note(60, 100)));
+        assert_eq!(play(note(61, 90)), Some(note(60, 90)));
+
+        // C4 keeps sounding until both keys are let go of
+        assert_eq!(play(note(61, 0)), None);
+        assert_eq!(play(note(60, 0)), Some(note(60, 0)));
+    }
+}
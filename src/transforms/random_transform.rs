@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::Transform;
+use crate::{
+    midi_event::{MIDIEvent, MIDIRouterEvent, NoteEvent},
+    scheduler::SchedulerHandler,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RandomTransformOptions {
+    /// Chance of every note going through, between 0.0 and 1.0. Note offs always follow their
+    /// note on, and other events always go through. Defaults to 1.0
+    probability: Option<f64>,
+    /// Delays notes by up to this many milliseconds. Note offs are delayed as much as their note
+    /// on, so notes keep their length. Other events aren't delayed, to keep them in order
+    jitter: Option<u64>,
+    /// Moves note on velocities up or down by up to this much
+    velocity: Option<u8>,
+    /// Chance of a note jumping up or down by some octaves, between 0.0 and 1.0. Defaults to 0.0
+    octave_probability: Option<f64>,
+    /// Octaves notes can jump by. Defaults to 1
+    octaves: Option<u8>,
+    /// Makes the randomness reproducible by always starting from the same seed
+    seed: Option<u64>,
+}
+
+// What happened to a note on, for its note off to follow
+struct PlayedNote {
+    note: u8,
+    delay: u64,
+}
+
+pub struct RandomTransform {
+    probability: f64,
+    jitter: u64,
+    velocity: u8,
+    octave_probability: f64,
+    octaves: u8,
+    rng: StdRng,
+    // Note ons by device, channel and note. `None` for the ones that were dropped
+    notes: HashMap<(String, u8, u8), Option<PlayedNote>>,
+}
+
+impl RandomTransform {
+    pub fn from_config(options: RandomTransformOptions) -> Self {
+        Self {
+            probability: options.probability.unwrap_or(1.0).clamp(0.0, 1.0),
+            jitter: options.jitter.unwrap_or(0),
+            velocity: options.velocity.unwrap_or(0),
+            octave_probability: options.octave_probability.unwrap_or(0.0).clamp(0.0, 1.0),
+            octaves: options.octaves.unwrap_or(1).max(1),
+            rng: options
+                .seed
+                .map_or_else(StdRng::from_entropy, StdRng::seed_from_u64),
+            notes: HashMap::new(),
+        }
+    }
+
+    fn passes(&mut self) -> bool {
+        self.probability >= 1.0 || self.rng.gen_bool(self.probability)
+    }
+
+    fn delay(&mut self) -> u64 {
+        match self.jitter {
+            0 => 0,
+            jitter => self.rng.gen_range(0..=jitter),
+        }
+    }
+
+    fn note_on(&mut self, note: &NoteEvent) -> Option<(NoteEvent, u64)> {
+        if !self.passes() {
+            return None;
+        }
+
+        let mut note = note.clone();
+
+        if self.velocity > 0 {
+            let offset = self
+                .rng
+                .gen_range(-(self.velocity as i16)..=self.velocity as i16);
+            note.velocity = (note.velocity as i16 + offset).clamp(1, 127) as u8;
+        }
+
+        if self.octave_probability > 0.0 && self.rng.gen_bool(self.octave_probability) {
+            let octaves = self.rng.gen_range(1..=self.octaves as i16);
+            let octaves = if self.rng.gen_bool(0.5) {
+                octaves
+            } else {
+                -octaves
+            };
+            let jumped = note.note as i16 + octaves * 12;
+
+            // Jumping out of range would lose the note, so stay put instead
+            if (0..=127).contains(&jumped) {
+                note.note = jumped as u8;
+            }
+        }
+
+        Some((note, self.delay()))
+    }
+}
+
+impl Transform for RandomTransform {
+    fn on_message(
+        &mut self,
+        mut message: MIDIRouterEvent,
+        scheduler: &SchedulerHandler,
+    ) -> Option<MIDIRouterEvent> {
+        let delay = match &mut message.event {
+            MIDIEvent::NoteOn(note) => {
+                let key = (message.device.clone(), note.channel, note.note);
+                let played = self.note_on(note);
+
+                self.notes.insert(
+                    key,
+                    played.as_ref().map(|(note, delay)| PlayedNote {
+                        note: note.note,
+                        delay: *delay,
+                    }),
+                );
+
+                let (played, delay) = played?;
+                *note = played;
+
+                delay
+            }
+
+            MIDIEvent::NoteOff(note) => {
+                let key = (message.device.clone(), note.channel, note.note);
+
+                match self.notes.remove(&key) {
+                    Some(Some(played)) => {
+                        note.note = played.note;
+
+                        played.delay
+                    }
+                    // Its note on was dropped
+                    Some(None) => return None,
+                    None => 0,
+                }
+            }
+
+            _ => 0,
+        };
+
+        if delay == 0 {
+            return Some(message);
+        }
+
+        scheduler.send_later(message, delay);
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RandomTransform, RandomTransformOptions};
+    use crate::{
+        app::MIDIMapperEvent,
+        midi_event::{note_event, Controller, MIDIEvent, MIDIRouterEvent, NoteEvent},
+        scheduler::Scheduler,
+        transforms::Transform,
+    };
+
+    fn transform(seed: u64) -> RandomTransform {
+        RandomTransform::from_config(RandomTransformOptions {
+            probability: Some(0.5),
+            jitter: None,
+            velocity: Some(20),
+            octave_probability: Some(0.5),
+            octaves: Some(2),
+            seed: Some(seed),
+        })
+    }
+
+    fn play(transform: &mut RandomTransform, note: u8, velocity: u8) -> Option<MIDIEvent> {
+        let (_scheduler, scheduler_handler) = Scheduler::new();
+
+        transform
+            .process_message(
+                MIDIMapperEvent::RouterMessage(MIDIRouterEvent {
+                    device: "keys".into(),
+                    event: note_event(note, velocity),
+                }),
+                &scheduler_handler,
+            )
+            .map(|message| message.event)
+    }
+
+    #[test]
+    fn is_reproducible_with_a_seed() {
+        let (mut first, mut second) = (transform(42), transform(42));
+
+        (0..50).for_each(|note| {
+            assert_eq!(
+                play(&mut first, note + 40, 100),
+                play(&mut second, note + 40, 100)
+            );
+        });
+    }
+
+    #[test]
+    fn note_offs_follow_their_note_on() {
+        let mut transform = transform(7);
+
+        (40..90).for_each(|note| match play(&mut transform, note, 100) {
+            Some(MIDIEvent::NoteOn(played)) => {
+                assert_eq!(
+                    play(&mut transform, note, 0),
+                    Some(MIDIEvent::NoteOff(NoteEvent {
+                        velocity: 0,
+                        ..played
+                    }))
+                );
+            }
+            _ => assert_eq!(play(&mut transform, note, 0), None),
+        });
+    }
+    #[test]
+    fn leaves_other_events_alone() {
+        let mut transform = RandomTransform::from_config(RandomTransformOptions {
+            probability: Some(0.0),
+            jitter: Some(1000),
+            velocity: None,
+            octave_probability: None,
+            octaves: None,
+            seed: Some(1),
+        });
+        let (_scheduler, scheduler_handler) = Scheduler::new();
+        let controller = MIDIRouterEvent {
+            device: "knobs".into(),
+            event: MIDIEvent::Controller(Controller {
+                channel: 0,
+                controller: 1,
+                value: 64,
+            }),
+        };
+
+        assert_eq!(
+            transform.process_message(
+                MIDIMapperEvent::RouterMessage(controller.clone()),
+                &scheduler_handler
+            ),
+            Some(controller)
+        );
+        assert_eq!(play(&mut transform, 60, 100), None);
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::Transform;
+use crate::{
+    midi_event::{MIDIEvent, MIDIRouterEvent, Note, NoteEvent},
+    scheduler::SchedulerHandler,
+    tempo::PPQN,
+};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub enum RatchetSelect {
+    /// Picks the subdivision from this controller, its values spread evenly over `subdivisions`
+    Controller(u8),
+    /// Picks the subdivision from how hard the notes are pressed, spread evenly over
+    /// `subdivisions`. Polyphonic aftertouch picks it for every note on its own
+    Aftertouch,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RatchetTransformOptions {
+    /// Subdivisions held notes can be repeated at, in beats. Defaults to `[0.25]`
+    subdivisions: Option<Vec<f64>>,
+    /// How the subdivision is picked while playing. The first one is used when not set
+    select: Option<RatchetSelect>,
+    /// Hits played on every subdivision. Defaults to 1
+    ratchets: Option<u8>,
+    /// Hits played on every subdivision for some notes, e.g. `{ C1: 2, D1: 3 }`, instead of
+    /// `ratchets`
+    note_ratchets: Option<HashMap<Note, u8>>,
+    /// How long every hit lasts, as a percentage of the time until the next one. Defaults to 50
+    gate: Option<f64>,
+}
+
+struct HeldNote {
+    device: String,
+    note: NoteEvent,
+    subdivision: usize,
+    ratchets: u8,
+    // Clock pulses since the note was pressed
+    pulse: u64,
+    sounding: bool,
+}
+
+pub struct RatchetTransform {
+    subdivisions: Vec<f64>,
+    select: Option<RatchetSelect>,
+    ratchets: u8,
+    note_ratchets: HashMap<u8, u8>,
+    gate: f64,
+    // Subdivision picked for new notes
+    subdivision: usize,
+    held_notes: Vec<HeldNote>,
+}
+
+impl HeldNote {
+    fn event(&self, event: MIDIEvent) -> MIDIRouterEvent {
+        MIDIRouterEvent {
+            device: self.device.clone(),
+            event,
+        }
+    }
+}
+
+impl RatchetTransform {
+    pub fn from_config(options: RatchetTransformOptions) -> Self {
+        Self {
+            subdivisions: options
+                .subdivisions
+                .filter(|subdivisions| !subdivisions.is_empty())
+                .unwrap_or(vec![0.25]),
+            select: options.select,
+            ratchets: options.ratchets.unwrap_or(1).max(1),
+            note_ratchets: options
+                .note_ratchets
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(note, ratchets)| (note.into(), ratchets.max(1)))
+                .collect(),
+            gate: options.gate.unwrap_or(50.0).clamp(1.0, 100.0),
+            subdivision: 0,
+            held_notes: vec![],
+        }
+    }
+
+    fn pick_subdivision(&self, value: u8) -> usize {
+        value as usize * self.subdivisions.len() / 128
+    }
+
+    /// Whether the note should be hit or let go of on its current pulse
+    fn step(&self, held: &HeldNote) -> (bool, bool) {
+        let period = (self.subdivisions[held.subdivision] * PPQN)
+            .round()
+            .max(1.0) as u64;
+        let ratchets = (held.ratchets as u64).min(period);
+        let phase = held.pulse % period;
+
+        // Hits are spread evenly over the subdivision
+        let hit = (0..ratchets).find(|hit| phase < (hit + 1) * period / ratchets);
+        let Some(hit) = hit else {
+            return (false, false);
+        };
+        let start = hit * period / ratchets;
+        let length = (hit + 1) * period / ratchets - start;
+        let gate = ((length as f64 * self.gate / 100.0).round() as u64).max(1);
+
+        (phase == start, phase == start + gate)
+    }
+}
+
+impl Transform for RatchetTransform {
+    fn get_tempo_subdiv(&self) -> Option<f64> {
+        // Every clock pulse
+        Some(1.0 / PPQN)
+    }
+
+    fn on_tick(&mut self, scheduler: &SchedulerHandler) -> Option<MIDIRouterEvent> {
+        for index in 0..self.held_notes.len() {
+            self.held_notes[index].pulse += 1;
+
+            let (is_hit, is_release) = self.step(&self.held_notes[index]);
+            let held = &mut self.held_notes[index];
+
+            if held.sounding && (is_hit || is_release) {
+                scheduler.send_now(held.event(MIDIEvent::NoteOff(held.note.get_note_off())));
+                held.sounding = false;
+            }
+
+            if is_hit {
+                scheduler.send_now(held.event(MIDIEvent::NoteOn(held.note.clone())));
+                held.sounding = true;
+            }
+        }
+
+        None
+    }
+
+    fn on_message(
+        &mut self,
+        message: MIDIRouterEvent,
+        _scheduler: &SchedulerHandler,
+    ) -> Option<MIDIRouterEvent> {
+        match (&message.event, &self.select) {
+            (MIDIEvent::NoteOn(note), _) => {
+                // The first hit is the note itself
+                self.held_notes.push(HeldNote {
+                    device: message.device.clone(),
+                    note: note.clone(),
+                    subdivision: self.subdivision,
+                    ratchets: *self.note_ratchets.get(&note.note).unwrap_or(&self.ratchets),
+                    pulse: 0,
+                    sounding: true,
+                });
+
+                Some(message)
+            }
+
+            (MIDIEvent::NoteOff(note), _) => {
+                let is_held = |held: &HeldNote| {
+                    held.device == message.device
+                        && held.note.channel == note.channel
+                        && held.note.note == note.note
+                };
+
+                // Only let go of it if a hit is still sounding, the note off being sent already
+                let sounding = self
+                    .held_notes
+                    .iter()
+                    .any(|held| is_held(held) && held.sounding);
+                self.held_notes.retain(|held| !is_held(held));
+
+                sounding.then_some(message)
+            }
+
+            (MIDIEvent::Controller(controller), Some(RatchetSelect::Controller(number)))
+                if controller.controller == *number =>
+            {
+                self.subdivision = self.pick_subdivision(controller.value);
+                self.held_notes
+                    .iter_mut()
+                    .for_each(|held| held.subdivision = self.subdivision);
+
+                None
+            }
+
+            (MIDIEvent::ChannelPressure(pressure), Some(RatchetSelect::Aftertouch)) => {
+                let subdivision = self.pick_subdivision(pressure.pressure);
+
+                self.held_notes
+                    .iter_mut()
+                    .filter(|held| {
+                        held.device == message.device && held.note.channel == pressure.channel
+                    })
+                    .for_each(|held| held.subdivision = subdivision);
+
+                None
+            }
+
+            (MIDIEvent::PolyphonicPressure(pressure), Some(RatchetSelect::Aftertouch)) => {
+                let subdivision = self.pick_subdivision(pressure.pressure);
+
+                self.held_notes
+                    .iter_mut()
+                    .filter(|held| {
+                        held.device == message.device
+                            && held.note.channel == pressure.channel
+                            && held.note.note == pressure.note
+                    })
+                    .for_each(|held| held.subdivision = subdivision);
+
+                None
+            }
+
+            _ => Some(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RatchetSelect, RatchetTransform, RatchetTransformOptions};
+    use crate::{
+        app::MIDIMapperEvent,
+        midi_event::{note_event, Controller, MIDIEvent, MIDIRouterEvent},
+        scheduler::Scheduler,
+        tempo::PPQN,
+        transforms::Transform,
+    };
+
+    fn note(velocity: u8) -> MIDIEvent {
+        note_event(36, velocity)
+    }
+
+    #[test]
+    fn repeats_held_notes() {
+        let mut transform = RatchetTransform::from_config(RatchetTransformOptions {
+            // 8 and 4 clock pulses
+            subdivisions: Some(vec![8.0 / PPQN, 4.0 / PPQN]),
+            select: Some(RatchetSelect::Controller(1)),
+            ratchets: Some(2),
+            note_ratchets: None,
+            gate: None,
+        });
+        let (scheduler, scheduler_handler) = Scheduler::new();
+        // Plays an event, if any, then lets the clock run for some pulses
+        let mut play = |event: Option<MIDIEvent>, pulses: usize| {
+            let message = event.map(|event| {
+                MIDIMapperEvent::RouterMessage(MIDIRouterEvent {
+                    device: "pads".into(),
+                    event,
+                })
+            });
+
+            let mut events: Vec<_> = message
+                .into_iter()
+                .chain((0..pulses).map(|_| MIDIMapperEvent::Tick))
+                .filter_map(|message| transform.process_message(message, &scheduler_handler))
+                .collect();
+            events.extend(scheduler.receiver.drain());
+
+            events
+                .into_iter()
+                .map(|message| message.event)
+                .collect::<Vec<_>>()
+        };
+
+        // Two hits every 8 pulses, lasting half the 4 pulses between them
+        assert_eq!(play(Some(note(100)), 0), [note(100)]);
+        assert_eq!(play(None, 2), [note(0)]);
+        assert_eq!(play(None, 2), [note(100)]);
+        assert_eq!(play(None, 4), [note(0), note(100)]);
+
+        // Then every 4 pulses
+        assert_eq!(
+            play(
+                Some(MIDIEvent::Controller(Controller {
+                    channel: 0,
+                    controller: 1,
+                    value: 127,
+                })),
+                0
+            ),
+            []
+        );
+        assert_eq!(play(None, 4), [note(0), note(100), note(0), note(100)]);
+
+        // Released while a hit is sounding
+        assert_eq!(play(Some(note(0)), 0), [note(0)]);
+        assert_eq!(play(None, 4), []);
+    }
+}
@@ -0,0 +1,392 @@
+use super::Transform;
+use crate::{
+    midi_event::{MIDIEvent, MIDIRouterEvent, Note, NoteEvent},
+    scheduler::SchedulerHandler,
+    tempo::StepTimer,
+};
+use rand::Rng;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::time::Duration;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SequencerStepOptions {
+    /// Note to play, e.g. `C3`. Steps without a note are rests
+    note: Option<Note>,
+    /// Defaults to the track's velocity
+    velocity: Option<u8>,
+    /// How long the note lasts, as a percentage of the step length. Defaults to the track's gate
+    gate: Option<f64>,
+    /// Chance of the note being played, between 0.0 and 1.0. Defaults to 1.0
+    probability: Option<f64>,
+    /// Keeps the previous note playing through this step. Defaults to false
+    tie: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum StepKeyword {
+    Rest,
+    Tie,
+}
+
+impl TryFrom<String> for StepKeyword {
+    type Error = String;
+
+    fn try_from(keyword: String) -> Result<Self, Self::Error> {
+        match keyword.to_lowercase().as_str() {
+            "rest" => Ok(Self::Rest),
+            "tie" => Ok(Self::Tie),
+            _ => Err(format!("Invalid sequencer step: '{keyword}'")),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum SequencerStep {
+    /// A note, e.g. `C3`
+    Note(Note),
+    /// `Rest` or `Tie`, in any case
+    Keyword(#[schemars(with = "String")] StepKeyword),
+    Full(SequencerStepOptions),
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SequencerTrackOptions {
+    steps: Vec<SequencerStep>,
+    /// Steps played before the track loops, which can differ between tracks to play polymeters.
+    /// Steps past the end of `steps` are rests. Defaults to the number of steps
+    length: Option<usize>,
+    /// Defaults to 0
+    channel: Option<u8>,
+    /// Defaults to 100
+    velocity: Option<u8>,
+    /// Defaults to 50
+    gate: Option<f64>,
+    output_device: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SequencerTransformOptions {
+    subdivision: f64,
+    tracks: Vec<SequencerTrackOptions>,
+    /// Incoming notes transpose the pattern by how far they are from this note, e.g. `C3`. The
+    /// pattern stays transposed until the next note. Incoming notes pass through when not set
+    transpose_root: Option<Note>,
+    /// Milliseconds notes last until the step length is known. Defaults to 100
+    note_duration: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StepKind {
+    Note(u8),
+    Rest,
+    Tie,
+}
+
+#[derive(Debug)]
+struct Step {
+    kind: StepKind,
+    velocity: u8,
+    gate: f64,
+    probability: f64,
+}
+
+#[derive(Debug)]
+struct Track {
+    steps: Vec<Step>,
+    length: usize,
+    channel: u8,
+    output_device: String,
+    position: usize,
+    // Note held through the following tied steps
+    sounding: Option<NoteEvent>,
+}
+
+impl Step {
+    fn from_config(step: SequencerStep, velocity: u8, gate: f64) -> Self {
+        let options = match step {
+            SequencerStep::Note(note) => SequencerStepOptions {
+                note: Some(note),
+                velocity: None,
+                gate: None,
+                probability: None,
+                tie: None,
+            },
+            SequencerStep::Keyword(keyword) => SequencerStepOptions {
+                note: None,
+                velocity: None,
+                gate: None,
+                probability: None,
+                tie: Some(keyword == StepKeyword::Tie),
+            },
+            SequencerStep::Full(options) => options,
+        };
+
+        let kind = match options.note {
+            _ if options.tie.unwrap_or(false) => StepKind::Tie,
+            Some(note) => StepKind::Note(note.into()),
+            None => StepKind::Rest,
+        };
+
+        Self {
+            kind,
+            velocity: options.velocity.unwrap_or(velocity).clamp(1, 127),
+            gate: options.gate.unwrap_or(gate),
+            probability: options.probability.unwrap_or(1.0).clamp(0.0, 1.0),
+        }
+    }
+
+    /// How long the note lasts, ending before the next step even with a gate above 100
+    fn duration(&self, step_duration: Option<Duration>, note_duration: u64) -> u64 {
+        match step_duration {
+            Some(step_duration) => {
+                let step = step_duration.as_millis() as u64;
+                let gated = (step_duration.as_secs_f64() * 1000.0 * self.gate / 100.0) as u64;
+
+                gated.min(step.saturating_sub(1)).max(1)
+            }
+            None => note_duration,
+        }
+    }
+}
+
+impl Track {
+    fn from_config(options: SequencerTrackOptions) -> Self {
+        let velocity = options.velocity.unwrap_or(100);
+        let gate = options.gate.unwrap_or(50.0);
+        let length = options.length.unwrap_or(options.steps.len());
+
+        Self {
+            steps: options
+                .steps
+                .into_iter()
+                .map(|step| Step::from_config(step, velocity, gate))
+                .collect(),
+            length,
+            channel: options.channel.unwrap_or(0),
+            output_device: options.output_device,
+            position: 0,
+            sounding: None,
+        }
+    }
+
+    fn note_off(&self, note: &NoteEvent) -> MIDIRouterEvent {
+        MIDIRouterEvent {
+            device: self.output_device.clone(),
+            event: MIDIEvent::NoteOff(note.get_note_off()),
+        }
+    }
+
+    fn kind_at(&self, position: usize) -> StepKind {
+        self.steps
+            .get(position)
+            .map_or(StepKind::Rest, |step| step.kind)
+    }
+
+    /// Plays the current step and moves on to the next one
+    fn advance(
+        &mut self,
+        transpose: i16,
+        step_duration: Option<Duration>,
+        note_duration: u64,
+        scheduler: &SchedulerHandler,
+    ) {
+        if self.length == 0 {
+            return;
+        }
+
+        let position = self.position;
+        self.position = (position + 1) % self.length;
+
+        let kind = self.kind_at(position);
+        if kind == StepKind::Tie {
+            return;
+        }
+
+        if let Some(note) = self.sounding.take() {
+            scheduler.send_now(self.note_off(&note));
+        }
+
+        let (StepKind::Note(note), Some(step)) = (kind, self.steps.get(position)) else {
+            return;
+        };
+        let note = note as i16 + transpose;
+
+        if !(0..=127).contains(&note)
+            || (step.probability < 1.0 && !rand::thread_rng().gen_bool(step.probability))
+        {
+            return;
+        }
+
+        let note = NoteEvent {
+            channel: self.channel,
+            note: note as u8,
+            velocity: step.velocity,
+        };
+
+        scheduler.send_now(MIDIRouterEvent {
+            device: self.output_device.clone(),
+            event: MIDIEvent::NoteOn(note.clone()),
+        });
+
+        // Tied notes are let go of on the first step that isn't a tie
+        if self.kind_at(self.position) == StepKind::Tie {
+            self.sounding = Some(note);
+            return;
+        }
+
+        scheduler.send_later(
+            self.note_off(&note),
+            step.duration(step_duration, note_duration),
+        );
+    }
+}
+
+pub struct SequencerTransform {
+    subdivision: f64,
+    tracks: Vec<Track>,
+    transpose_root: Option<u8>,
+    transpose: i16,
+    note_duration: u64,
+    step_timer: StepTimer,
+}
+
+impl SequencerTransform {
+    pub fn from_config(options: SequencerTransformOptions) -> Self {
+        Self {
+            subdivision: options.subdivision,
+            tracks: options.tracks.into_iter().map(Track::from_config).collect(),
+            transpose_root: options.transpose_root.map(Note::into),
+            transpose: 0,
+            note_duration: options.note_duration.unwrap_or(100),
+            step_timer: StepTimer::default(),
+        }
+    }
+}
+
+impl Transform for SequencerTransform {
+    fn get_tempo_subdiv(&self) -> Option<f64> {
+        Some(self.subdivision)
+    }
+
+    fn on_tick(&mut self, scheduler: &SchedulerHandler) -> Option<MIDIRouterEvent> {
+        self.step_timer.tick();
+
+        for track in self.tracks.iter_mut() {
+            track.advance(
+                self.transpose,
+                self.step_timer.step(),
+                self.note_duration,
+                scheduler,
+            );
+        }
+
+        None
+    }
+
+    fn on_message(
+        &mut self,
+        message: MIDIRouterEvent,
+        _scheduler: &SchedulerHandler,
+    ) -> Option<MIDIRouterEvent> {
+        let Some(root) = self.transpose_root else {
+            return Some(message);
+        };
+
+        match message.event {
+            MIDIEvent::NoteOn(note) => {
+                self.transpose = note.note as i16 - root as i16;
+
+                None
+            }
+            MIDIEvent::NoteOff(_) => None,
+            _ => Some(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        SequencerStep, SequencerTrackOptions, SequencerTransform, SequencerTransformOptions, Step,
+    };
+    use crate::{
+        app::MIDIMapperEvent,
+        midi_event::{note_event as note, MIDIEvent, MIDIRouterEvent, Note},
+        scheduler::Scheduler,
+        transforms::Transform,
+    };
+    use std::time::Duration;
+
+    fn track(steps: &[&str], length: Option<usize>) -> SequencerTrackOptions {
+        SequencerTrackOptions {
+            steps: steps
+                .iter()
+                .map(|step| serde_yaml::from_str(step).unwrap())
+                .collect(),
+            length,
+            channel: None,
+            velocity: None,
+            gate: None,
+            output_device: "synth".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn plays_tracks_with_ties_and_transposition() {
+        let mut transform = SequencerTransform::from_config(SequencerTransformOptions {
+            subdivision: 0.25,
+            tracks: vec![track(&["C4", "tie", "REST"], None), track(&["G4"], Some(2))],
+            transpose_root: Some(Note::C4),
+            note_duration: Some(1000),
+        });
+        let (scheduler, scheduler_handler) = Scheduler::new();
+        let mut play = |event: Option<MIDIEvent>| {
+            let message = match event {
+                Some(event) => MIDIMapperEvent::RouterMessage(MIDIRouterEvent {
+                    device: "keys".into(),
+                    event,
+                }),
+                None => MIDIMapperEvent::Tick,
+            };
+            transform.process_message(message, &scheduler_handler);
+
+            scheduler
+                .receiver
+                .drain()
+                .map(|message| {
+                    assert_eq!(message.device, "synth");
+                    message.event
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(play(None), [note(60, 100), note(67, 100)]);
+        assert_eq!(play(None), []);
+        assert_eq!(play(None), [note(60, 0), note(67, 100)]);
+
+        // Notes played transpose the pattern instead of going through
+        assert_eq!(play(Some(note(62, 100))), []);
+        assert_eq!(play(Some(note(62, 0))), []);
+        assert_eq!(play(None), [note(62, 100)]);
+    }
+
+    #[test]
+    fn ends_notes_before_the_next_step() {
+        let step = |gate| Step::from_config(SequencerStep::Note(Note::C4), 100, gate);
+        let step_duration = Some(Duration::from_millis(200));
+
+        assert_eq!(step(50.0).duration(step_duration, 1000), 100);
+        assert_eq!(step(100.0).duration(step_duration, 1000), 199);
+        assert_eq!(step(150.0).duration(step_duration, 1000), 199);
+        assert_eq!(step(150.0).duration(None, 1000), 1000);
+    }
+
+    #[test]
+    fn rejects_unknown_steps() {
+        assert!(serde_yaml::from_str::<SequencerStep>("Hold").is_err());
+        assert!(serde_yaml::from_str::<SequencerStep>("{ note: H4 }").is_err());
+    }
+}
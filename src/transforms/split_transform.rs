@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::Transform;
+use crate::{
+    midi_event::{MIDIEvent, MIDIRouterEvent, Note, NoteEvent},
+    scheduler::SchedulerHandler,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SplitZoneOptions {
+    /// Lowest and highest note of the zone, inclusive, e.g. `["C-1", "B3"]`. Every note if omitted
+    pub notes: Option<(Note, Note)>,
+    /// Lowest and highest velocity of the zone, inclusive. Every velocity if omitted
+    pub velocities: Option<(u8, u8)>,
+    /// Channel the zone plays on. Keeps the incoming channel if omitted
+    pub channel: Option<u8>,
+    /// Semitones to shift the notes of this zone by
+    pub transpose: Option<i8>,
+    /// Output device alias the zone plays on. Keeps the incoming device if omitted
+    pub output_device: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SplitTransformOptions {
+    pub zones: Vec<SplitZoneOptions>,
+    /// Play every matching zone instead of only the first one. Defaults to false
+    pub layer: Option<bool>,
+}
+
+struct Zone {
+    notes: (u8, u8),
+    velocities: (u8, u8),
+    channel: Option<u8>,
+    transpose: i8,
+    output_device: Option<String>,
+}
+
+impl Zone {
+    fn from_config(options: SplitZoneOptions) -> Self {
+        Self {
+            notes: options
+                .notes
+                .map(|(low, high)| (low.into(), high.into()))
+                .unwrap_or((0, 127)),
+            velocities: options.velocities.unwrap_or((0, 127)),
+            channel: options.channel,
+            transpose: options.transpose.unwrap_or(0),
+            output_device: options.output_device,
+        }
+    }
+
+    fn contains(&self, note: &NoteEvent) -> bool {
+        (self.notes.0..=self.notes.1).contains(&note.note)
+            && (self.velocities.0..=self.velocities.1).contains(&note.velocity)
+    }
+
+    /// Moves the message to this zone, unless the transposed note falls out of range
+    fn route(&self, message: &MIDIRouterEvent) -> Option<MIDIRouterEvent> {
+        let mut message = MIDIRouterEvent {
+            device: self
+                .output_device
+                .clone()
+                .unwrap_or_else(|| message.device.clone()),
+            event: message.event.clone(),
+        };
+
+        if let Some(channel) = self.channel {
+            message.event.set_channel(channel);
+        }
+
+        if let MIDIEvent::NoteOn(ref mut note) | MIDIEvent::NoteOff(ref mut note) = message.event {
+            let target = note.note as i16 + self.transpose as i16;
+            note.note = u8::try_from(target).ok().filter(|n| *n <= 127)?;
+        }
+
+        Some(message)
+    }
+}
+
+// Splits a keyboard into zones by note and velocity range, each with its own routing
+pub struct SplitTransform {
+    zones: Vec<Zone>,
+    layer: bool,
+    // Zones picked by every note on, so the note off follows them even if the zones no longer
+    // match, e.g. because of a different release velocity
+    sounding: HashMap<(String, u8, u8), Vec<usize>>,
+}
+
+impl SplitTransform {
+    pub fn from_config(options: SplitTransformOptions) -> Self {
+        Self {
+            zones: options.zones.into_iter().map(Zone::from_config).collect(),
+            layer: options.layer.unwrap_or(false),
+            sounding: HashMap::new(),
+        }
+    }
+
+    fn matching_zones(&self, note: &NoteEvent) -> Vec<usize> {
+        let matching = self
+            .zones
+            .iter()
+            .enumerate()
+            .filter(|(_, zone)| zone.contains(note))
+            .map(|(index, _)| index);
+
+        if self.layer {
+            matching.collect()
+        } else {
+            matching.take(1).collect()
+        }
+    }
+}
+
+impl Transform for SplitTransform {
+    fn on_message(
+        &mut self,
+        message: MIDIRouterEvent,
+        scheduler: &SchedulerHandler,
+    ) -> Option<MIDIRouterEvent> {
+        let zones = match &message.event {
+            MIDIEvent::NoteOn(note) => {
+                let zones = self.matching_zones(note);
+                self.sounding.insert(
+                    (message.device.clone(), note.channel, note.note),
+                    zones.clone(),
+                );
+
+                zones
+            }
+
+            MIDIEvent::NoteOff(note) => {
+                self.sounding
+                    .remove(&(message.device.clone(), note.channel, note.note))?
+            }
+
+            // Pressure follows the zones of the note it applies to
+            MIDIEvent::PolyphonicPressure(pressure) => self
+                .sounding
+                .get(&(message.device.clone(), pressure.channel, pressure.note))?
+                .clone(),
+
+            // Everything else (sustain, pitch bend...) reaches every zone
+            _ => (0..self.zones.len()).collect(),
+        };
+
+        let mut destinations: Vec<(String, u8)> = vec![];
+        zones
+            .iter()
+            .filter_map(|zone| self.zones[*zone].route(&message))
+            .for_each(|routed| {
+                let destination = (routed.device.clone(), routed.event.get_channel());
+
+                // Layered zones sharing a destination only need non-note events once
+                let is_note = matches!(
+                    routed.event,
+                    MIDIEvent::NoteOn(_) | MIDIEvent::NoteOff(_) | MIDIEvent::PolyphonicPressure(_)
+                );
+                if !is_note && destinations.contains(&destination) {
+                    return;
+                }
+
+                destinations.push(destination);
+                scheduler.send_now(routed);
+            });
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SplitTransform, SplitTransformOptions, SplitZoneOptions};
+    use crate::{
+        app::MIDIMapperEvent,
+        midi_event::{note_event, Controller, MIDIEvent, MIDIRouterEvent},
+        scheduler::Scheduler,
+        transforms::Transform,
+    };
+
+    fn zone(notes: (&str, &str), velocities: Option<(u8, u8)>, channel: u8) -> SplitZoneOptions {
+        SplitZoneOptions {
+            notes: Some((notes.0.parse().unwrap(), notes.1.parse().unwrap())),
+            velocities,
+            channel: Some(channel),
+            transpose: None,
+            output_device: None,
+        }
+    }
+
+    fn note(note: u8, velocity: u8) -> MIDIMapperEvent {
+        MIDIMapperEvent::RouterMessage(MIDIRouterEvent {
+            device: "keys".into(),
+            event: note_event(note, velocity),
+        })
+    }
+
+    // (device, channel, note) of every routed note
+    fn split(
+        transform: &mut SplitTransform,
+        events: Vec<MIDIMapperEvent>,
+    ) -> Vec<(String, u8, Option<u8>)> {
+        let (scheduler, scheduler_handler) = Scheduler::new();
+
+        events.into_iter().for_each(|event| {
+            assert!(transform
+                .process_message(event, &scheduler_handler)
+                .is_none());
+        });
+
+        scheduler
+            .receiver
+            .drain()
+            .map(|message| {
+                let note = match &message.event {
+                    MIDIEvent::NoteOn(note) | MIDIEvent::NoteOff(note) => Some(note.note),
+                    _ => None,
+                };
+
+                (message.device, message.event.get_channel(), note)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn routes_notes_to_their_zone() {
+        let mut transform = SplitTransform::from_config(SplitTransformOptions {
+            zones: vec![
+                SplitZoneOptions {
+                    transpose: Some(12),
+                    output_device: Some("bass".into()),
+                    ..zone(("C-1", "B3"), None, 1)
+                },
+                zone(("C4", "G9"), None, 2),
+            ],
+            layer: None,
+        });
+
+        assert_eq!(
+            split(&mut transform, vec![note(40, 100), note(60, 100)]),
+            vec![("bass".into(), 1, Some(52)), ("keys".into(), 2, Some(60))]
+        );
+    }
+
+    #[test]
+    fn layers_zones_and_routes_note_offs_to_their_note_on() {
+        let mut transform = SplitTransform::from_config(SplitTransformOptions {
+            zones: vec![
+                zone(("0", "127"), Some((0, 63)), 1),
+                zone(("0", "127"), Some((64, 127)), 2),
+                zone(("0", "127"), None, 3),
+            ],
+            layer: Some(true),
+        });
+
+        assert_eq!(
+            split(&mut transform, vec![note(60, 100), note(60, 0)]),
+            vec![
+                ("keys".into(), 2, Some(60)),
+                ("keys".into(), 3, Some(60)),
+                ("keys".into(), 2, Some(60)),
+                ("keys".into(), 3, Some(60)),
+            ]
+        );
+
+        // Other events reach every zone
+        let sustain = MIDIMapperEvent::RouterMessage(MIDIRouterEvent {
+            device: "keys".into(),
+            event: MIDIEvent::Controller(Controller {
+                channel: 0,
+                controller: 64,
+                value: 127,
+            }),
+        });
+        assert_eq!(split(&mut transform, vec![sustain]).len(), 3);
+    }
+}
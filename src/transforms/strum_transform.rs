@@ -0,0 +1,274 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::Transform;
+use crate::{
+    midi_event::{MIDIEvent, MIDIRouterEvent, NoteEvent},
+    scheduler::SchedulerHandler,
+    tempo::{StepTimer, PPQN},
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+pub enum StrumDirection {
+    /// Lowest to highest
+    #[default]
+    Up,
+    /// Highest to lowest
+    Down,
+    /// Up, then down on the next chord, and so on
+    Alternate,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct StrumTransformOptions {
+    /// Notes coming in within this many milliseconds of the first one are strummed together.
+    /// Defaults to 30
+    window: Option<u64>,
+    /// Defaults to `Up`
+    direction: Option<StrumDirection>,
+    /// Milliseconds between the first and last note of a chord. Defaults to 100
+    time: Option<u64>,
+    /// Spreads the chord over this many beats instead, `time` being used until the tempo is known
+    beats: Option<f64>,
+    /// Added to the velocity of every note after the first, e.g. -10 to strum softer and softer.
+    /// Defaults to 0
+    velocity_ramp: Option<i8>,
+}
+
+struct BufferedNote {
+    device: String,
+    note: NoteEvent,
+    // Set when the note was let go of before being played
+    note_off: Option<NoteEvent>,
+}
+
+pub struct StrumTransform {
+    window: Duration,
+    direction: StrumDirection,
+    time: u64,
+    beats: Option<f64>,
+    velocity_ramp: i8,
+    // Whether the next alternate strum goes up
+    next_up: bool,
+    // Measures clock pulses
+    step_timer: StepTimer,
+    // Chord being gathered, and when its first note came in
+    chord: Vec<BufferedNote>,
+    chord_started: Option<Instant>,
+    // How much every strummed note was delayed, by device, channel and note
+    delays: HashMap<(String, u8, u8), u64>,
+}
+
+impl StrumTransform {
+    pub fn from_config(options: StrumTransformOptions) -> Self {
+        Self {
+            window: Duration::from_millis(options.window.unwrap_or(30)),
+            direction: options.direction.unwrap_or_default(),
+            time: options.time.unwrap_or(100),
+            beats: options.beats,
+            velocity_ramp: options.velocity_ramp.unwrap_or(0),
+            next_up: true,
+            step_timer: StepTimer::default(),
+            chord: vec![],
+            chord_started: None,
+            delays: HashMap::new(),
+        }
+    }
+
+    fn send(scheduler: &SchedulerHandler, message: MIDIRouterEvent, delay: u64) {
+        match delay {
+            0 => scheduler.send_now(message),
+            delay => scheduler.send_later(message, delay),
+        }
+    }
+
+    /// Plays the chord gathered so far, spread over the strum time
+    fn strum(&mut self, scheduler: &SchedulerHandler) {
+        let mut chord: Vec<BufferedNote> = self.chord.drain(..).collect();
+        self.chord_started = None;
+
+        let up = match self.direction {
+            StrumDirection::Up => true,
+            StrumDirection::Down => false,
+            StrumDirection::Alternate => {
+                self.next_up = !self.next_up;
+
+                !self.next_up
+            }
+        };
+
+        chord.sort_by_key(|buffered| buffered.note.note);
+        if !up {
+            chord.reverse();
+        }
+
+        let gaps = chord.len().saturating_sub(1).max(1) as u64;
+
+        chord.into_iter().enumerate().for_each(|(index, buffered)| {
+            let delay = self.time * index as u64 / gaps;
+            let velocity = buffered.note.velocity as i16 + self.velocity_ramp as i16 * index as i16;
+            let note = NoteEvent {
+                velocity: velocity.clamp(1, 127) as u8,
+                ..buffered.note
+            };
+
+            Self::send(
+                scheduler,
+                MIDIRouterEvent {
+                    device: buffered.device.clone(),
+                    event: MIDIEvent::NoteOn(note.clone()),
+                },
+                delay,
+            );
+
+            match buffered.note_off {
+                // Already let go of, right after being played
+                Some(note_off) => scheduler.send_later(
+                    MIDIRouterEvent {
+                        device: buffered.device,
+                        event: MIDIEvent::NoteOff(note_off),
+                    },
+                    delay + 1,
+                ),
+                None => {
+                    self.delays
+                        .insert((buffered.device, note.channel, note.note), delay);
+                }
+            }
+        });
+    }
+}
+
+impl Transform for StrumTransform {
+    fn get_tempo_subdiv(&self) -> Option<f64> {
+        // Every clock pulse, to play chords as soon as their window is over
+        Some(1.0 / PPQN)
+    }
+
+    fn on_tick(&mut self, scheduler: &SchedulerHandler) -> Option<MIDIRouterEvent> {
+        let now = Instant::now();
+        self.step_timer.tick();
+        if let (Some(beats), Some(pulse)) = (self.beats, self.step_timer.step()) {
+            self.time = (pulse.as_secs_f64() * 1000.0 * beats * PPQN) as u64;
+        }
+
+        if self
+            .chord_started
+            .is_some_and(|started| now - started >= self.window)
+        {
+            self.strum(scheduler);
+        }
+
+        None
+    }
+
+    fn on_message(
+        &mut self,
+        message: MIDIRouterEvent,
+        scheduler: &SchedulerHandler,
+    ) -> Option<MIDIRouterEvent> {
+        match &message.event {
+            MIDIEvent::NoteOn(note) => {
+                self.chord_started.get_or_insert_with(Instant::now);
+                self.chord.push(BufferedNote {
+                    device: message.device.clone(),
+                    note: note.clone(),
+                    note_off: None,
+                });
+
+                None
+            }
+
+            MIDIEvent::NoteOff(note) => {
+                let is_note = |device: &String, buffered: &NoteEvent| {
+                    *device == message.device
+                        && buffered.channel == note.channel
+                        && buffered.note == note.note
+                };
+
+                // Not played yet, it will be let go of once it is
+                if let Some(buffered) = self
+                    .chord
+                    .iter_mut()
+                    .find(|buffered| is_note(&buffered.device, &buffered.note))
+                {
+                    buffered.note_off = Some(note.clone());
+
+                    return None;
+                }
+
+                // Delayed as much as its note on, so it lasts as long as it was held
+                match self
+                    .delays
+                    .remove(&(message.device.clone(), note.channel, note.note))
+                {
+                    Some(delay) if delay > 0 => {
+                        scheduler.send_later(message, delay);
+
+                        None
+                    }
+                    _ => Some(message),
+                }
+            }
+
+            _ => Some(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StrumDirection, StrumTransform, StrumTransformOptions};
+    use crate::{
+        app::MIDIMapperEvent,
+        midi_event::{note_event as note, MIDIRouterEvent},
+        scheduler::Scheduler,
+        transforms::Transform,
+    };
+
+    #[test]
+    fn strums_alternating_chords() {
+        let mut transform = StrumTransform::from_config(StrumTransformOptions {
+            window: Some(0),
+            direction: Some(StrumDirection::Alternate),
+            time: Some(0),
+            beats: None,
+            velocity_ramp: Some(-10),
+        });
+        let (scheduler, scheduler_handler) = Scheduler::new();
+        let mut strum = |notes: &[u8]| {
+            notes.iter().for_each(|played| {
+                let result = transform.process_message(
+                    MIDIMapperEvent::RouterMessage(MIDIRouterEvent {
+                        device: "keys".into(),
+                        event: note(*played, 100),
+                    }),
+                    &scheduler_handler,
+                );
+
+                assert_eq!(result, None);
+            });
+            transform.process_message(MIDIMapperEvent::Tick, &scheduler_handler);
+
+            scheduler
+                .receiver
+                .drain()
+                .map(|message| message.event)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            strum(&[64, 60, 67]),
+            [note(60, 100), note(64, 90), note(67, 80)]
+        );
+        assert_eq!(
+            strum(&[64, 60, 67]),
+            [note(67, 100), note(64, 90), note(60, 80)]
+        );
+    }
+}
@@ -1,13 +1,22 @@
 use schemars::JsonSchema;
 use serde::Deserialize;
 
-use crate::{app::MIDIMapperEvent, midi_event::MIDIRouterEvent, scheduler::SchedulerHandler};
+use crate::{
+    app::MIDIMapperEvent, midi_event::MIDIRouterEvent, note_ledger::NoteLedgerHandle,
+    scheduler::SchedulerHandler,
+};
 
 use super::{
-    arpeggio_transform::ArpeggioTransformOptions, distribute_transform::DistributeTransformOptions,
-    inspect_transform::InspectTransformOptions, mirror_transform::MirrorTransformOptions,
-    output_transform::OutputTransformOptions, wasm_transform::WasmTransformOptions,
-    FilterTransformOptions, MapTransformOptions,
+    arpeggio_transform::ArpeggioTransformOptions, bend_transform::BendTransformOptions,
+    chord_transform::ChordTransformOptions, convert_transform::ConvertTransformOptions,
+    delay_transform::DelayTransformOptions, distribute_transform::DistributeTransformOptions,
+    euclid_transform::EuclidTransformOptions, inspect_transform::InspectTransformOptions,
+    mirror_transform::MirrorTransformOptions, output_transform::OutputTransformOptions,
+    quantize_transform::QuantizeTransformOptions, random_transform::RandomTransformOptions,
+    ratchet_transform::RatchetTransformOptions, sequencer_transform::SequencerTransformOptions,
+    split_transform::SplitTransformOptions, strum_transform::StrumTransformOptions,
+    transpose_transform::TransposeTransformOptions, velocity_transform::VelocityTransformOptions,
+    wasm_transform::WasmTransformOptions, FilterTransformOptions, MapTransformOptions,
 };
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -21,11 +30,27 @@ pub enum SerializedTransform {
     Output(OutputTransformOptions),
     Inspect(InspectTransformOptions),
     Mirror(MirrorTransformOptions),
+    Transpose(TransposeTransformOptions),
+    Quantize(QuantizeTransformOptions),
+    Chord(ChordTransformOptions),
+    Velocity(VelocityTransformOptions),
+    Split(SplitTransformOptions),
+    Convert(ConvertTransformOptions),
+    Bend(BendTransformOptions),
+    Sequencer(SequencerTransformOptions),
+    Euclid(EuclidTransformOptions),
+    Random(RandomTransformOptions),
+    Delay(DelayTransformOptions),
+    Ratchet(RatchetTransformOptions),
+    Strum(StrumTransformOptions),
 }
 
 pub trait Transform {
     fn set_scheduler(&mut self, _scheduler: SchedulerHandler) {}
 
+    // Gives access to the notes currently sounding on the output devices
+    fn set_note_ledger(&mut self, _note_ledger: NoteLedgerHandle) {}
+
     fn get_tempo_subdiv(&self) -> Option<f64> {
         None
     }
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::Transform;
+use crate::{
+    midi_event::{MIDIEvent, MIDIRouterEvent, Note},
+    scheduler::SchedulerHandler,
+};
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub enum OutOfRange {
+    /// Use the lowest/highest note available
+    #[default]
+    Clamp,
+    /// Ignore the note altogether
+    Drop,
+    /// Move the note by octaves until it fits
+    WrapOctave,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TransposeTransformOptions {
+    pub semitones: Option<i8>,
+    pub octaves: Option<i8>,
+    /// Per channel transposition in semitones, replacing `semitones` and `octaves` for that channel
+    pub channels: Option<Vec<(u8, i8)>>,
+    pub out_of_range: Option<OutOfRange>,
+}
+
+pub struct TransposeTransform {
+    offset: i16,
+    channels: HashMap<u8, i16>,
+    out_of_range: OutOfRange,
+    // Transposed note for every note on we've let through (None if it was dropped), so its note
+    // off ends up on the same note
+    sounding: HashMap<(String, u8, u8), Option<u8>>,
+}
+
+impl TransposeTransform {
+    pub fn from_config(options: TransposeTransformOptions) -> Self {
+        Self {
+            offset: options.semitones.unwrap_or(0) as i16
+                + options.octaves.unwrap_or(0) as i16 * 12,
+            channels: options
+                .channels
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(channel, offset)| (channel, offset as i16))
+                .collect(),
+            out_of_range: options.out_of_range.unwrap_or_default(),
+            sounding: HashMap::new(),
+        }
+    }
+
+    fn transpose(&self, channel: u8, note: u8) -> Option<u8> {
+        let offset = *self.channels.get(&channel).unwrap_or(&self.offset);
+        let target = note as i16 + offset;
+
+        match self.out_of_range {
+            OutOfRange::Clamp => Some((Note::from(note) + offset.clamp(-127, 127) as i8).into()),
+            OutOfRange::Drop => (0..=127).contains(&target).then_some(target as u8),
+            OutOfRange::WrapOctave => {
+                let octaves = match target {
+                    t if t > 127 => (t - 127 + 11) / 12,
+                    t if t < 0 => -((-t + 11) / 12),
+                    _ => 0,
+                };
+
+                Some((target - octaves * 12) as u8)
+            }
+        }
+    }
+}
+
+impl Transform for TransposeTransform {
+    fn on_message(
+        &mut self,
+        mut message: MIDIRouterEvent,
+        _scheduler: &SchedulerHandler,
+    ) -> Option<MIDIRouterEvent> {
+        let device = message.device.clone();
+
+        let target = match message.event {
+            MIDIEvent::NoteOn(ref mut note) => {
+                let target = self.transpose(note.channel, note.note);
+                self.sounding
+                    .insert((device, note.channel, note.note), target);
+
+                target.map(|target| note.note = target)
+            }
+
+            MIDIEvent::NoteOff(ref mut note) => {
+                let target = self
+                    .sounding
+                    .remove(&(device, note.channel, note.note))
+                    .unwrap_or_else(|| self.transpose(note.channel, note.note));
+
+                target.map(|target| note.note = target)
+            }
+
+            MIDIEvent::PolyphonicPressure(ref mut pressure) => {
+                let target = self
+                    .sounding
+                    .get(&(device, pressure.channel, pressure.note))
+                    .copied()
+                    .unwrap_or_else(|| self.transpose(pressure.channel, pressure.note));
+
+                target.map(|target| pressure.note = target)
+            }
+
+            _ => Some(()),
+        };
+
+        target.map(|_| message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OutOfRange, TransposeTransform, TransposeTransformOptions};
+    use crate::{
+        app::MIDIMapperEvent,
+        midi_event::{MIDIEvent, NoteEvent, Wrap},
+        scheduler::Scheduler,
+        transforms::Transform,
+    };
+
+    fn note(channel: u8, note: u8, velocity: u8) -> MIDIMapperEvent {
+        MIDIMapperEvent::RouterMessage(
+            NoteEvent {
+                channel,
+                note,
+                velocity,
+            }
+            .wrap(),
+        )
+    }
+
+    fn transposed_notes(
+        transform: &mut TransposeTransform,
+        events: Vec<MIDIMapperEvent>,
+    ) -> Vec<Option<u8>> {
+        let (_scheduler, scheduler_handler) = Scheduler::new();
+
+        events
+            .into_iter()
+            .map(|event| {
+                transform
+                    .process_message(event, &scheduler_handler)
+                    .map(|message| match message.event {
+                        MIDIEvent::NoteOn(note) | MIDIEvent::NoteOff(note) => note.note,
+                        _ => unreachable!(),
+                    })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn transposes_by_semitones_and_octaves() {
+        let mut transform = TransposeTransform::from_config(TransposeTransformOptions {
+            semitones: Some(2),
+            octaves: Some(-1),
+            channels: Some(vec![(3, 7)]),
+            out_of_range: None,
+        });
+
+        assert_eq!(
+            transposed_notes(&mut transform, vec![note(0, 60, 100), note(3, 60, 100)]),
+            vec![Some(50), Some(67)]
+        );
+    }
+
+    #[test]
+    fn out_of_range_policies() {
+        let notes = || vec![note(0, 120, 100), note(0, 5, 100)];
+        let options = |out_of_range, semitones| {
+            TransposeTransform::from_config(TransposeTransformOptions {
+                semitones: Some(semitones),
+                octaves: None,
+                channels: None,
+                out_of_range: Some(out_of_range),
+            })
+        };
+
+        assert_eq!(
+            transposed_notes(&mut options(OutOfRange::Clamp, 10), notes()),
+            vec![Some(127), Some(15)]
+        );
+        assert_eq!(
+            transposed_notes(&mut options(OutOfRange::Drop, 10), notes()),
+            vec![None, Some(15)]
+        );
+        assert_eq!(
+            transposed_notes(&mut options(OutOfRange::WrapOctave, 10), notes()),
+            vec![Some(118), Some(15)]
+        );
+        assert_eq!(
+            transposed_notes(&mut options(OutOfRange::WrapOctave, -10), notes()),
+            vec![Some(110), Some(7)]
+        );
+    }
+
+    #[test]
+    fn note_offs_match_their_note_on() {
+        let mut transform = TransposeTransform::from_config(TransposeTransformOptions {
+            semitones: Some(12),
+            octaves: None,
+            channels: None,
+            out_of_range: Some(OutOfRange::Drop),
+        });
+
+        let result = transposed_notes(&mut transform, vec![note(0, 60, 100), note(0, 120, 100)]);
+        assert_eq!(result, vec![Some(72), None]);
+
+        // The offset changes while the notes are held
+        transform.offset = 2;
+
+        let result = transposed_notes(&mut transform, vec![note(0, 60, 0), note(0, 120, 0)]);
+        assert_eq!(result, vec![Some(72), None]);
+    }
+
+    #[test]
+    fn rejects_channel_offsets_beyond_a_byte() {
+        let options = serde_yaml::from_str::<TransposeTransformOptions>("channels: [[1, 200]]");
+        assert!(options.is_err());
+
+        let options = serde_yaml::from_str::<TransposeTransformOptions>("channels: [[1, -12]]");
+        assert_eq!(options.unwrap().channels, Some(vec![(1, -12)]));
+    }
+}
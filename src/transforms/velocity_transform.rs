@@ -0,0 +1,185 @@
+use rand::Rng;
+use schemars::JsonSchema;
+use serde::{de, Deserialize, Deserializer};
+
+use super::Transform;
+use crate::{
+    curve::Curve,
+    midi_event::{MIDIEvent, MIDIRouterEvent},
+    scheduler::SchedulerHandler,
+};
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct VelocityTransformOptions {
+    /// Every note gets this velocity, ignoring `curve` and `table`
+    pub fixed: Option<u8>,
+    pub curve: Option<Curve>,
+    /// 128 velocities, looked up by the incoming velocity. Takes precedence over `curve`
+    #[serde(default, deserialize_with = "deserialize_table")]
+    #[schemars(length(equal = 128))]
+    pub table: Option<Vec<u8>>,
+    /// Lowest velocity notes end up with, the curve being scaled to start from it. Without `min`
+    /// or `max` a linear curve leaves velocities as they are
+    pub min: Option<u8>,
+    /// Highest velocity notes end up with, the curve being scaled to end on it. Defaults to 127
+    pub max: Option<u8>,
+    /// Randomly moves the resulting velocity up or down by up to this amount
+    pub humanize: Option<u8>,
+}
+
+fn deserialize_table<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<u8>>, D::Error> {
+    let table = Option::<Vec<u8>>::deserialize(deserializer)?;
+
+    match &table {
+        Some(values) if values.len() != 128 => Err(de::Error::invalid_length(
+            values.len(),
+            &"a velocity table with 128 values",
+        )),
+        _ => Ok(table),
+    }
+}
+
+// Reshapes the velocity of note ons, note offs are left alone
+pub struct VelocityTransform {
+    fixed: Option<u8>,
+    curve: Curve,
+    table: Option<Vec<u8>>,
+    min: u8,
+    max: u8,
+    humanize: u8,
+}
+
+impl VelocityTransform {
+    pub fn from_config(options: VelocityTransformOptions) -> Self {
+        let min = options.min.unwrap_or(0).min(127);
+
+        Self {
+            fixed: options.fixed,
+            curve: options.curve.unwrap_or_default(),
+            table: options.table,
+            min,
+            max: options.max.unwrap_or(127).clamp(min.max(1), 127),
+            humanize: options.humanize.unwrap_or(0),
+        }
+    }
+
+    fn get_velocity(&self, velocity: u8) -> u8 {
+        let velocity = match (self.fixed, &self.table) {
+            (Some(fixed), _) => fixed as f64,
+            (None, Some(table)) => table[velocity as usize] as f64,
+            (None, None) => {
+                let shaped = self.curve.apply(velocity as f64 / 127.0);
+
+                self.min as f64 + shaped * (self.max as f64 - self.min as f64)
+            }
+        };
+
+        let offset = match self.humanize {
+            0 => 0,
+            humanize => rand::thread_rng().gen_range(-(humanize as i16)..=humanize as i16),
+        };
+
+        // At least 1, as a note on with no velocity would be a note off
+        (velocity.round() as i16 + offset).clamp(self.min.max(1) as i16, self.max as i16) as u8
+    }
+}
+
+impl Transform for VelocityTransform {
+    fn on_message(
+        &mut self,
+        mut message: MIDIRouterEvent,
+        _scheduler: &SchedulerHandler,
+    ) -> Option<MIDIRouterEvent> {
+        if let MIDIEvent::NoteOn(ref mut note) = message.event {
+            note.velocity = self.get_velocity(note.velocity);
+        }
+
+        Some(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{VelocityTransform, VelocityTransformOptions};
+    use crate::curve::Curve;
+
+    fn options() -> VelocityTransformOptions {
+        VelocityTransformOptions {
+            fixed: None,
+            curve: None,
+            table: None,
+            min: None,
+            max: None,
+            humanize: None,
+        }
+    }
+
+    fn velocities(options: VelocityTransformOptions) -> [u8; 4] {
+        let transform = VelocityTransform::from_config(options);
+
+        [1, 32, 64, 127].map(|velocity| transform.get_velocity(velocity))
+    }
+
+    #[test]
+    fn shapes_velocities() {
+        assert_eq!(velocities(options()), [1, 32, 64, 127]);
+        assert_eq!(
+            velocities(VelocityTransformOptions {
+                curve: Some(Curve::Exponential),
+                ..options()
+            }),
+            [1, 8, 24, 127]
+        );
+        assert_eq!(
+            velocities(VelocityTransformOptions {
+                table: Some((0..128).rev().collect()),
+                ..options()
+            }),
+            [126, 95, 63, 1]
+        );
+        assert_eq!(
+            velocities(VelocityTransformOptions {
+                fixed: Some(90),
+                ..options()
+            }),
+            [90, 90, 90, 90]
+        );
+    }
+
+    #[test]
+    fn keeps_velocities_within_min_and_max() {
+        let limits = || VelocityTransformOptions {
+            min: Some(40),
+            max: Some(100),
+            ..options()
+        };
+
+        assert_eq!(velocities(limits()), [40, 55, 70, 100]);
+        assert_eq!(
+            velocities(VelocityTransformOptions {
+                table: Some((0..128).rev().collect()),
+                ..limits()
+            }),
+            [100, 95, 63, 40]
+        );
+        assert_eq!(
+            velocities(VelocityTransformOptions {
+                fixed: Some(127),
+                ..limits()
+            }),
+            [100, 100, 100, 100]
+        );
+    }
+
+    #[test]
+    fn rejects_tables_without_128_values() {
+        let error = serde_yaml::from_str::<VelocityTransformOptions>("table: [1, 2, 3]")
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("invalid length 3"));
+        assert!(serde_yaml::from_str::<VelocityTransformOptions>("curve: Linear").is_ok());
+    }
+}
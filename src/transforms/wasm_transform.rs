@@ -1,6 +1,8 @@
 use super::Transform;
 use crate::{
-    midi_event::{Controller, MIDIEvent, MIDIRouterEvent, NoteEvent},
+    midi_event::{
+        Controller, Controller14, MIDIEvent, MIDIRouterEvent, NoteEvent, ParameterNumber,
+    },
     scheduler::SchedulerHandler,
 };
 use schemars::JsonSchema;
@@ -120,6 +122,8 @@ impl Transform for WasmTransform {
 
 fn values_to_midi_event(m: i32, v1: i32, v2: i32, v3: i32) -> Option<MIDIEvent> {
     let [local_v1, local_v2, local_v3]: [u8; 3] = [v1, v2, v3].map(|v| v.try_into().unwrap_or(0));
+    // 14-bit controllers and parameter numbers carry wider values
+    let [wide_v2, wide_v3]: [u16; 2] = [v2, v3].map(|v| v.clamp(0, 0x3FFF) as u16);
 
     let message_code = m;
     match message_code {
@@ -135,7 +139,7 @@ fn values_to_midi_event(m: i32, v1: i32, v2: i32, v3: i32) -> Option<MIDIEvent>
             NoteEvent {
                 channel: local_v1,
                 note: local_v2,
-                velocity: 0,
+                velocity: local_v3,
             }
         })),
 
@@ -145,6 +149,24 @@ fn values_to_midi_event(m: i32, v1: i32, v2: i32, v3: i32) -> Option<MIDIEvent>
             value: local_v3,
         })),
 
+        7 => Some(MIDIEvent::Controller14(Controller14 {
+            channel: local_v1,
+            controller: local_v2,
+            value: wide_v3,
+        })),
+
+        8 => Some(MIDIEvent::Nrpn(ParameterNumber {
+            channel: local_v1,
+            parameter: wide_v2,
+            value: wide_v3,
+        })),
+
+        9 => Some(MIDIEvent::Rpn(ParameterNumber {
+            channel: local_v1,
+            parameter: wide_v2,
+            value: wide_v3,
+        })),
+
         _ => None,
     }
 }
@@ -186,5 +208,67 @@ fn midi_event_to_values(m: MIDIEvent) -> [Value; 4] {
         MIDIEvent::ChannelPressure(_) => todo!(),
         MIDIEvent::ProgramChange(_) => todo!(),
         MIDIEvent::PitchBend(_) => todo!(),
+        MIDIEvent::Controller14(Controller14 {
+            channel,
+            controller,
+            value,
+        }) => [
+            Value::I32(7),
+            Value::I32(channel as i32),
+            Value::I32(controller as i32),
+            Value::I32(value as i32),
+        ],
+        MIDIEvent::Nrpn(ParameterNumber {
+            channel,
+            parameter,
+            value,
+        }) => [
+            Value::I32(8),
+            Value::I32(channel as i32),
+            Value::I32(parameter as i32),
+            Value::I32(value as i32),
+        ],
+        MIDIEvent::Rpn(ParameterNumber {
+            channel,
+            parameter,
+            value,
+        }) => [
+            Value::I32(9),
+            Value::I32(channel as i32),
+            Value::I32(parameter as i32),
+            Value::I32(value as i32),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{midi_event_to_values, values_to_midi_event};
+    use crate::midi_event::{Controller14, MIDIEvent, ParameterNumber};
+
+    #[test]
+    fn round_trips_fourteen_bit_events() {
+        let parameter = ParameterNumber {
+            channel: 2,
+            parameter: 300,
+            value: 16383,
+        };
+
+        [
+            MIDIEvent::Controller14(Controller14 {
+                channel: 1,
+                controller: 7,
+                value: 9000,
+            }),
+            MIDIEvent::Nrpn(parameter.clone()),
+            MIDIEvent::Rpn(parameter),
+        ]
+        .into_iter()
+        .for_each(|event| {
+            let [m, v1, v2, v3] =
+                midi_event_to_values(event.clone()).map(|value| value.unwrap_i32());
+
+            assert_eq!(values_to_midi_event(m, v1, v2, v3), Some(event));
+        });
     }
 }